@@ -1,24 +1,11 @@
-use itertools::Itertools;
-use std::fs;
-use std::io;
-mod computer;
-use computer::Computer;
-use computer::State;
+use intcode::{input_to_registers, Computer, State};
 use std::collections::HashMap;
 
 fn main() {
     // let result = part1();
     // println!("part1 {}", result);
 
-    let result = part2();
-    // println!("part2 {}", result);
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Tile {
-    x: i32,
-    y: i32,
-    color: Color,
+    part2();
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
@@ -37,9 +24,9 @@ impl From<i64> for Color {
     }
 }
 
-impl Into<i64> for Color {
-    fn into(self) -> i64 {
-        match self {
+impl From<Color> for i64 {
+    fn from(val: Color) -> Self {
+        match val {
             Color::Black => 0,
             Color::White => 1,
         }
@@ -65,7 +52,7 @@ impl Robot {
                 }
             }
             Rotation::Right => { 
-                Self::FACING_ORDER[((current_position as isize + 1) % 4).abs() as usize]
+                Self::FACING_ORDER[((current_position as isize + 1) % 4).unsigned_abs()]
             }
         };
         dbg!(self.facing);
@@ -105,18 +92,13 @@ impl From<i64> for Rotation {
     }
 }
 
+fn next_output(comp: &mut Computer) -> Option<i64> {
+    comp.next_output().expect("intcode program faulted")
+}
+
+#[allow(dead_code)]
 fn part1() -> usize {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let registers = input
-        .split(",")
-        .map(|x| match x.trim().parse::<i64>() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("could not parse {}", x);
-                panic!("could not parse");
-            }
-        })
-        .collect::<Vec<_>>();
+    let registers = input_to_registers();
 
     let mut map = HashMap::new();
     let mut robot = Robot { position: (0, 0), facing: Facing::Up, };
@@ -125,13 +107,15 @@ fn part1() -> usize {
 
     while comp.state != State::Halted {
         let current_tile = map.entry(robot.position).or_insert(Color::Black);
-        comp.input(current_tile.clone() as i64);
-        comp.run();
-        let color = Color::from(comp.outputs.pop_front().expect("no color"));
-        moves.push((robot.position, color.clone()));
+        comp.input(*current_tile as i64);
+        let color = match next_output(&mut comp) {
+            Some(color) => Color::from(color),
+            None => break,
+        };
+        moves.push((robot.position, color));
         *current_tile = color;
 
-        let direction = Rotation::from(comp.outputs.pop_front().expect("no direction"));
+        let direction = Rotation::from(next_output(&mut comp).expect("no direction"));
         robot.execute(direction);
     }
 
@@ -141,17 +125,7 @@ fn part1() -> usize {
 }
 
 fn part2() {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let registers = input
-        .split(",")
-        .map(|x| match x.trim().parse::<i64>() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("could not parse {}", x);
-                panic!("could not parse");
-            }
-        })
-        .collect::<Vec<_>>();
+    let registers = input_to_registers();
 
     let mut map = HashMap::new();
     map.insert((0, 5), Color::White);
@@ -161,13 +135,15 @@ fn part2() {
 
     while comp.state != State::Halted {
         let current_tile = map.entry(robot.position).or_insert(Color::Black);
-        comp.input(current_tile.clone() as i64);
-        comp.run();
-        let color = Color::from(comp.outputs.pop_front().expect("no color"));
-        moves.push((robot.position, color.clone()));
+        comp.input(*current_tile as i64);
+        let color = match next_output(&mut comp) {
+            Some(color) => Color::from(color),
+            None => break,
+        };
+        moves.push((robot.position, color));
         *current_tile = color;
 
-        let direction = Rotation::from(comp.outputs.pop_front().expect("no direction"));
+        let direction = Rotation::from(next_output(&mut comp).expect("no direction"));
         robot.execute(direction);
     }
 
@@ -1,8 +1,5 @@
-use std::fs;
-use std::io;
-mod computer;
-use computer::Computer;
-use computer::State;
+use intcode::{input_to_registers, Computer, Tracer};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::cmp::Ordering;
@@ -14,13 +11,6 @@ fn main() {
     part2();
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Tile {
-    x: i32,
-    y: i32,
-    tile_type: Type,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 enum Type {
     Empty,
@@ -43,37 +33,23 @@ impl From<i64> for Type {
     }
 }
 
-fn input_to_registers() -> Vec<i64> {
-    let input = fs::read_to_string("input.txt").unwrap();
-    input
-        .split(",")
-        .map(|x| match x.trim().parse::<i64>() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("could not parse {}", x);
-                panic!("could not parse");
-            }
-        })
-        .collect::<Vec<_>>()
-}
-
 struct Game {
     map: HashMap<(i64, i64), Type>,
     ball_position: (i64, i64),
     paddle_position: (i64, i64),
     score: i64,
+    pending: Vec<i64>,
 }
 
 impl Game {
-    fn new(comp: &mut Computer) -> Self {
-        let mut game = Self {
+    fn new() -> Self {
+        Self {
             map: HashMap::new(),
             ball_position: (0, 0),
             paddle_position: (0, 0),
             score: 0,
-        };
-        game.update(comp);
-        game
+            pending: vec![],
+        }
     }
 
     fn insert(&mut self, x: i64, y: i64, item: Type) {
@@ -91,18 +67,25 @@ impl Game {
         self.map.insert((x, y), item);
     }
 
-    fn update(&mut self, comp: &mut Computer) {
-        comp.run();
-
-        while comp.outputs.len() > 0 {
-            let x = comp.outputs.pop_front().unwrap();
-            let y = comp.outputs.pop_front().unwrap();
-            let z = comp.outputs.pop_front().unwrap();
+    fn receive(&mut self, value: i64) {
+        self.pending.push(value);
+        if let [x, y, z] = self.pending[..] {
             if x == -1 && y == 0 {
                 self.score = z;
             } else {
                 self.insert(x, y, Type::from(z));
             }
+            self.pending.clear();
+        }
+    }
+
+    fn joystick(&self) -> i64 {
+        let (ball_x, _) = self.ball_position;
+        let (paddle_x, _) = self.paddle_position;
+        match ball_x.cmp(&paddle_x) {
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+            Ordering::Less => -1,
         }
     }
 }
@@ -131,10 +114,13 @@ impl fmt::Display for Game {
     }
 }
 
+#[allow(dead_code)]
 fn part1() -> usize {
-    let mut registers = input_to_registers();
+    let registers = input_to_registers();
     let mut comp = Computer::new(registers);
-    let game = Game::new(&mut comp);
+    comp.run().expect("intcode program faulted");
+    let mut game = Game::new();
+    comp.outputs.drain(..).for_each(|value| game.receive(value));
     game
         .map
         .values()
@@ -146,29 +132,18 @@ fn part2() -> usize {
     let mut registers = input_to_registers();
     registers[0] = 2;
     let mut comp = Computer::new(registers);
-    let mut game = Game::new(&mut comp);
-
-    while comp.state != State::Halted {
-        // let one_second = std::time::Duration::from_millis(10);
-        // std::thread::sleep(one_second);
-        // print!("{}[2J", 27 as char);
-        // println!("{}", game);
-
-        match comp.state {
-            State::Waiting => {
-                let (ball_x, _) = game.ball_position;
-                let (paddle_x, _) = game.paddle_position;
-                let input = match ball_x.cmp(&paddle_x) {
-                    Ordering::Equal => 0,
-                    Ordering::Greater => 1,
-                    Ordering::Less => -1,
-                };
-                comp.input(input);
-                game.update(&mut comp);
-            }
-            _ => break,
-        }
+    if let Some(tracer) = Tracer::from_env("day13") {
+        comp.set_tracer(tracer);
     }
-    println!("{}", game.score);
+
+    // the joystick follows the ball whenever the game asks for input
+    let game = RefCell::new(Game::new());
+    let mut comp = comp.with_io(
+        || Some(game.borrow().joystick()),
+        |value| game.borrow_mut().receive(value),
+    );
+    comp.run().expect("intcode program faulted");
+    comp.flush_trace().expect("could not write trace");
+    println!("{}", game.borrow().score);
     0
 }
@@ -1,10 +1,11 @@
-use std::fs;
-mod computer;
-use computer::Computer;
-use computer::State;
+use intcode::{input_to_registers, Computer};
 use std::collections::HashMap;
 use std::collections::VecDeque;
 
+mod droid {
+    include!(concat!(env!("OUT_DIR"), "/droid.rs"));
+}
+
 fn main() {
     part1();
     part2();
@@ -125,7 +126,7 @@ fn part2() {
     'bfs: while let Some(current) = queue.pop_front() {
         for direction in DIRECTIONS.iter() {
             if let Some(mut node) = iterate(&current.computer, direction, &current, &mut map) {
-                if map.get(&(-20, 14)).is_some() {
+                if map.contains_key(&(-20, 14)) {
                     queue.clear();
                     map.clear();
                     node.step = 0;
@@ -147,7 +148,7 @@ fn part2() {
 
     }
 
-    let max = map.values().max_by(|(_, step1), (_, step2)| step1.cmp(&step2)).unwrap().1;
+    let max = map.values().max_by(|(_, step1), (_, step2)| step1.cmp(step2)).unwrap().1;
     println!("part2: {}", max);
 }
 
@@ -162,7 +163,7 @@ fn iterate(computer: &Computer, direction: &Dir, current: &Node, map: &mut Map)
     let b: i64 = a.into();
     let mut computer = computer.clone();
     computer.input(b);
-    computer.run();
+    droid::run(&mut computer).expect("intcode program faulted");
     let output = computer.outputs.pop_front().expect("no tile type");
     let tile_type = Type::from(output);
     match tile_type {
@@ -222,3 +223,19 @@ fn visualize(map: &Map) {
         println!("{}", line.iter().collect::<String>());
     }
 }
+
+#[test]
+fn it_matches_the_interpreter() {
+    let mut interpreted = Computer::new(input_to_registers());
+    let mut translated = interpreted.clone();
+    for step in 0..2000 {
+        let direction = [1, 4, 2, 3][step * 7 % 11 % 4];
+        interpreted.input(direction);
+        translated.input(direction);
+        let expected = interpreted.run();
+        assert_eq!(droid::run(&mut translated), expected);
+        assert_eq!(translated.outputs, interpreted.outputs);
+        assert_eq!(translated.registers, interpreted.registers);
+        assert_eq!(translated.position(), interpreted.position());
+    }
+}
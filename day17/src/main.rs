@@ -1,7 +1,4 @@
-mod computer;
-
-use computer::State;
-use computer::Computer;
+use intcode::{input_to_registers, Ascii, Computer};
 
 #[derive(Clone, Eq, PartialEq)]
 enum Tile {
@@ -70,7 +67,7 @@ fn execute_order(facing: Facing, mut position: (usize, usize), order: &str) -> (
             }
         }
         Rotation::Right => {
-            FACING_ORDER[((current_position as isize + 1) % 4).abs() as usize]
+            FACING_ORDER[((current_position as isize + 1) % 4).unsigned_abs()]
         }
     };
     println!("{:?} {:?} {}", &facing, &new_facing, order);
@@ -95,16 +92,15 @@ fn execute_order(facing: Facing, mut position: (usize, usize), order: &str) -> (
     (new_facing, movements)
 }
 
+#[allow(dead_code)]
 type Map = Vec<Vec<Tile>>;
 
 fn main() {
-    // let input = computer::input_to_registers();
-    // let mut comp = Computer::new(input);
-    // comp.run();
-    //
-    // let result = comp.outputs.iter().map(|code| *code as u8 as char).collect::<String>();
+    // let input = input_to_registers();
+    // let mut ascii = Ascii::new(Computer::new(input));
+    // let result = ascii.read_text().expect("intcode program faulted").text;
     // println!("{}", result);
-    // let width = result.lines().nth(0).unwrap().chars().count();
+    // let width = result.lines().next().unwrap().chars().count();
     // let height = result.lines().count();
     // let mut map = vec![vec![Tile::Empty; width]; height];
     // result.lines().enumerate().for_each(|(y, line)| {
@@ -128,6 +124,7 @@ fn main() {
     part2()
 }
 
+#[allow(dead_code)]
 fn part2_working() {
     //L10L8R8L8R6
     //L10L8R8L8R6
@@ -141,12 +138,10 @@ fn part2_working() {
     //L8L10R6R8R8
     //L10L8R8L8R6
     //
-    let input = computer::input_to_registers();
-    let mut comp = Computer::new(input);
-    comp.run();
-
-    let result = comp.outputs.iter().map(|code| *code as u8 as char).collect::<String>();
-    let width = result.lines().nth(0).unwrap().chars().count();
+    let input = input_to_registers();
+    let mut ascii = Ascii::new(Computer::new(input));
+    let result = ascii.read_text().expect("intcode program faulted").text;
+    let width = result.lines().next().unwrap().chars().count();
     let height = result.lines().count();
     let mut map = vec![vec![Tile::Empty; width]; height];
     result.lines().enumerate().for_each(|(y, line)| {
@@ -194,7 +189,6 @@ fn part2_working() {
         }).collect::<String>();
         println!("{}", result);
     }
-    ()
 }
 
 fn part2() {
@@ -212,67 +206,54 @@ fn part2() {
     //
     //
     fn convert_to_input(input: &str) -> String {
-        let mut out = input.chars().map(|c| c.to_string()).collect::<Vec<String>>().join(",");
-        out.push('\n');
-        out
+        input.chars().map(|c| c.to_string()).collect::<Vec<String>>().join(",")
     }
-    let mut input = computer::input_to_registers();
+    let mut input = input_to_registers();
     input[0] = 2;
-    let mut comp = Computer::new(input);
-    comp.run();
-
-    let movement_routine = convert_to_input("AABCBCBCBA");
-    let mut routine_a = vec!["L","10","L","8","R","8","L","8","R","6"].into_iter().map(|c| c.to_string()).collect::<Vec<String>>().join(",");
-    routine_a.push('\n');
-    let routine_b = convert_to_input("R6R8R8");
-    let mut routine_c = vec!["R","6","R","6","L","8","L","10"].into_iter().map(|c| c.to_string()).collect::<Vec<String>>().join(",");
-    routine_c.push('\n');
-
-    for c in movement_routine.chars() {
-        comp.input((c as u8) as i64)
-    }
-    for c in routine_a.chars() {
-        comp.input((c as u8) as i64)
-    }
-    for c in routine_b.chars() {
-        comp.input((c as u8) as i64)
-    }
-    for c in routine_c.chars() {
-        comp.input((c as u8) as i64)
-    }
+    let mut ascii = Ascii::new(Computer::new(input));
+    ascii.read_until_prompt("Main:\n").expect("intcode program faulted");
 
-    comp.input(('n' as u8) as i64);
-    comp.input(('\n' as u8) as i64);
-    comp.run();
-    dbg!(comp.outputs);
+    ascii.send_line(&convert_to_input("AABCBCBCBA"));
+    ascii.send_line(&["L","10","L","8","R","8","L","8","R","6"].join(","));
+    ascii.send_line(&convert_to_input("R6R8R8"));
+    ascii.send_line(&["R","6","R","6","L","8","L","10"].join(","));
+    ascii.send_line("n");
+    let text = ascii.read_text().expect("intcode program faulted");
+    println!("{}", text.text);
+    dbg!(text.numbers);
 }
 
 #[rustfmt::skip]
+#[allow(dead_code)]
 const COORDINATES : [(i8, i8); 9]= [
     (-1, 1), (0, 1), (1, 1),
     (-1, 0), (0, 0),  (1, 0),
     (-1, -1),(0, -1), (1, -1)
 ];
 
+#[allow(dead_code)]
 const INTERSECTION_TILES : [Option<Tile>; 9]= [
     Some(Tile::Empty), Some(Tile::Scaff), Some(Tile::Empty),
     Some(Tile::Scaff), Some(Tile::Scaff), Some(Tile::Scaff),
     Some(Tile::Empty), Some(Tile::Scaff), Some(Tile::Empty),
 ];
 
+#[allow(dead_code)]
 fn detect_intersection(map: &Map, x: usize, y: usize) -> bool {
     COORDINATES.iter().map(|(x_offset, y_offset)| {
         safe_get(map, x, y, *x_offset, *y_offset)
     }).zip(INTERSECTION_TILES.iter()).all(|(actual, expected)| actual == expected.as_ref())
 }
 
+#[allow(dead_code)]
 fn safe_get(map: &Map, x: usize, y: usize, x_offset: i8, y_offset: i8) -> Option<&Tile> {
     map.get(safe_add(y, y_offset)?)?.get(safe_add(x, x_offset)?)
 }
 
+#[allow(dead_code)]
 fn safe_add(a: usize, b: i8) -> Option<usize>{
     if b.is_negative() {
-        a.checked_sub(b.abs() as usize)
+        a.checked_sub(b.unsigned_abs() as usize)
     } else {
         a.checked_add(b as usize)
     }
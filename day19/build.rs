@@ -0,0 +1,10 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=input.txt");
+    let program = intcode::load_registers("input.txt");
+    let out = Path::new(&env::var_os("OUT_DIR").unwrap()).join("beam.rs");
+    fs::write(out, intcode::translate(&program)).expect("could not write the translation");
+}
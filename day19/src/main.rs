@@ -1,20 +1,25 @@
-mod computer;
-use computer::input_to_registers;
-use computer::Computer;
+use intcode::{input_to_registers, run_batch_with, Computer};
+
+mod beam {
+    include!(concat!(env!("OUT_DIR"), "/beam.rs"));
+}
 
 fn main() {
     // println!("part1: {:?}", part1());
     println!("part2: {:?}", part2());
 }
 
+#[allow(dead_code)]
 fn part1() -> usize {
     let registers = input_to_registers();
-    let mut comp = Computer::new(registers);
+    let comp = Computer::new(registers);
     let mut canvas: [[usize; 50]; 50] = [[0; 50]; 50];
-    for x in 0..50 {
-        for y in 0..50 {
-            canvas[y as usize][x as usize] = attracted(&comp, x, y) as usize;
-        }
+    let probes = (0..50)
+        .flat_map(|x| (0..50).map(move |y| vec![x, y]))
+        .collect::<Vec<_>>();
+    for (probe, outputs) in probes.iter().zip(run_batch_with(&comp, &probes, beam::run)) {
+        let outputs = outputs.expect("intcode program faulted");
+        canvas[probe[1] as usize][probe[0] as usize] = outputs[0] as usize;
     }
 
     for row in canvas.iter() {
@@ -25,7 +30,7 @@ fn part1() -> usize {
 
 fn part2() -> i64 {
     let registers = input_to_registers();
-    let mut comp = Computer::new(registers);
+    let comp = Computer::new(registers);
     let (mut x, mut y) = (0, 0);
     loop {
         if !attracted(&comp, x, y + 99) {
@@ -46,10 +51,52 @@ fn attracted(comp: &Computer, x: i64, y: i64) -> bool {
     let mut new_comp = comp.clone();
     new_comp.input(x);
     new_comp.input(y);
-    new_comp.run();
+    beam::run(&mut new_comp).expect("intcode program faulted");
     match new_comp.outputs.pop_front().unwrap() {
         0 => false,
         1 => true,
         _ => unimplemented!(),
     }
 }
+
+#[test]
+fn it_matches_the_interpreter() {
+    let comp = Computer::new(input_to_registers());
+    for &(x, y) in &[(0, 0), (5, 7), (30, 40), (49, 49), (800, 900), (-1, 3)] {
+        let mut interpreted = comp.clone();
+        let mut translated = comp.clone();
+        for comp in [&mut interpreted, &mut translated].iter_mut() {
+            comp.input(x);
+            comp.input(y);
+        }
+        let expected = interpreted.run();
+        assert_eq!(beam::run(&mut translated), expected);
+        assert_eq!(translated.outputs, interpreted.outputs);
+        assert_eq!(translated.registers, interpreted.registers);
+        assert_eq!(translated.position(), interpreted.position());
+        assert_eq!(translated.relative_base(), interpreted.relative_base());
+    }
+}
+
+#[test]
+fn it_journals_translated_runs() {
+    use intcode::{Journal, State};
+
+    let program = input_to_registers();
+    let mut comp = Computer::new(program.clone());
+    comp.set_journal(Journal::new(1_000));
+    comp.input(30);
+    comp.input(40);
+    assert_eq!(beam::run(&mut comp), Ok(State::Halted));
+    let steps = comp.journal().unwrap().steps();
+    assert!(steps > 4);
+    assert_eq!(comp.rewind(4), 4);
+    assert_eq!(comp.rewind(steps), steps - 4);
+    // memory the run grew into stays, zeroed
+    let memory = comp.registers.to_vec();
+    assert_eq!(&memory[..program.len()], &program[..]);
+    assert!(memory[program.len()..].iter().all(|&value| value == 0));
+    assert_eq!(comp.position(), 0);
+    assert_eq!(comp.inputs, vec![30, 40]);
+    assert!(comp.outputs.is_empty());
+}
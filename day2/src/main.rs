@@ -5,6 +5,7 @@ fn main() {
     part2()
 }
 
+#[allow(dead_code)]
 fn part1() {
     let input = fs::read_to_string("input.txt").unwrap();
     let mut registers = input
@@ -48,7 +49,7 @@ fn part2() {
     }
 }
 
-fn run_registers(registers: &mut Vec<u32>) -> u32 {
+fn run_registers(registers: &mut [u32]) -> u32 {
     let mut position = 0;
     loop {
         match *registers.get(position).unwrap() {
@@ -87,7 +88,7 @@ fn run_registers(registers: &mut Vec<u32>) -> u32 {
     registers[0]
 }
 
-fn get_register_value(registers: &Vec<u32>, index: usize) -> u32 {
+fn get_register_value(registers: &[u32], index: usize) -> u32 {
     *registers
         .get(*registers.get(index).expect("couldn't reach register") as usize)
         .expect("couldn't reach register value")
@@ -109,3 +110,23 @@ fn it_works2() {
     run_registers(&mut registers);
     assert_eq!(registers, vec![2, 0, 0, 0, 99]);
 }
+
+#[test]
+fn it_conforms() {
+    use intcode::{corpus, Dialect, Outcome};
+    use std::convert::TryFrom;
+
+    let report = Dialect::DAY2.check(&corpus(), |case| {
+        let mut registers = case
+            .program
+            .iter()
+            .map(|&value| u32::try_from(value).unwrap())
+            .collect::<Vec<_>>();
+        run_registers(&mut registers);
+        Outcome {
+            outputs: vec![],
+            memory: Some(registers.into_iter().map(i64::from).collect()),
+        }
+    });
+    assert!(report.disagreements.is_empty(), "{}", report);
+}
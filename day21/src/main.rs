@@ -1,8 +1,4 @@
-mod computer;
-use computer::{input_to_registers, Computer};
-use std::collections::VecDeque;
-use std::fmt::write;
-use std::iter;
+use intcode::{input_to_registers, Ascii, Computer};
 
 fn main() {
     // part1();
@@ -14,24 +10,26 @@ enum Instruction {
     And(Register, Register),
     Or(Register, Register),
     Not(Register, Register),
+    #[allow(dead_code)]
     Walk,
     Run,
 }
 
-impl Into<String> for Instruction {
-    fn into(self) -> String {
+impl From<Instruction> for String {
+    fn from(val: Instruction) -> Self {
         use Instruction::*;
-        match self {
+        match val {
             And(a, b) => vec!["AND".to_string(), a.into(), b.into()],
             Or(a, b) => vec!["OR".to_string(), a.into(), b.into()],
             Not(a, b) => vec!["NOT".to_string(), a.into(), b.into()],
-            Walk => vec!["WALK\n".to_string()],
-            Run => vec!["RUN\n".to_string()],
+            Walk => vec!["WALK".to_string()],
+            Run => vec!["RUN".to_string()],
         }
         .join(" ")
     }
 }
 
+#[allow(dead_code)]
 #[derive(Clone)]
 enum Register {
     One,
@@ -47,10 +45,10 @@ enum Register {
     Jump,
 }
 
-impl Into<String> for Register {
-    fn into(self) -> String {
+impl From<Register> for String {
+    fn from(val: Register) -> Self {
         use Register::*;
-        match self {
+        match val {
             One => "A",
             Two => "B",
             Three => "C",
@@ -68,46 +66,32 @@ impl Into<String> for Register {
 }
 
 struct SpringProgram {
-    comp: Computer,
+    ascii: Ascii,
     instructions: Vec<Instruction>,
 }
 impl SpringProgram {
-    fn run(&mut self) {
-        self.instructions
-            .iter()
-            .cloned()
-            .map(|instruction| instruction.into())
-            .collect::<Vec<String>>()
-            .join("\n")
-            .chars()
-            .for_each(|c| {
-                self.comp.input((c as u8) as i64);
-            });
-
-        self.comp.run();
-        println!(
-            "{}",
-            self.comp
-                .outputs
-                .iter()
-                .map(|c| (*c as u8) as char)
-                .collect::<String>()
-        )
-    }
+    /// Prints what the droid saw and returns the hull damage it reported,
+    /// if it made it across.
+    fn run(&mut self) -> Vec<i64> {
+        for instruction in self.instructions.iter().cloned() {
+            self.ascii.send_line(&String::from(instruction));
+        }
 
-    fn outputs(&self) -> &VecDeque<i64> {
-        &self.comp.outputs
+        let text = self.ascii.read_text().expect("intcode program faulted");
+        println!("{}", text.text);
+        text.numbers
     }
 }
 
+#[allow(dead_code)]
 fn part1() {
     use Instruction::*;
     use Register::*;
     let registers = input_to_registers();
-    let mut comp = Computer::new(registers);
+    let ascii = Ascii::new(Computer::new(registers));
 
     let mut prog = SpringProgram {
-        comp,
+        ascii,
         instructions: vec![
             Not(Three, Jump),
             Or(Four, Temp),
@@ -118,18 +102,17 @@ fn part1() {
         ],
     };
 
-    prog.run();
-    dbg!(&prog.outputs());
+    dbg!(prog.run());
 }
 
 fn part2() {
     use Instruction::*;
     use Register::*;
     let registers = input_to_registers();
-    let mut comp = Computer::new(registers);
+    let ascii = Ascii::new(Computer::new(registers));
 
     let mut prog = SpringProgram {
-        comp,
+        ascii,
         instructions: vec![
             //Jump make sure you can jump again
             Not(Three, Jump),
@@ -146,6 +129,5 @@ fn part2() {
         ],
     };
 
-    prog.run();
-    dbg!(prog.outputs());
+    dbg!(prog.run());
 }
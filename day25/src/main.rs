@@ -1,18 +1,31 @@
-mod computer;
-use computer::{input_to_registers, load_registers, Computer, State};
-use std::fs;
-use std::fs::OpenOptions;
-use std::io::{stdin, Write};
-use std::iter;
+use intcode::{input_to_registers, Ascii, Computer, Snapshot, State};
 use itertools::Itertools;
+use std::collections::HashMap;
+use std::env;
+use std::io::stdin;
 
 fn main() {
-    part1();
+    if env::args().any(|arg| arg == "play") {
+        play();
+    } else {
+        part1();
+    }
 }
 
 struct Game<'a> {
-    comp: Computer,
+    ascii: Ascii,
     inputs: Vec<&'a str>,
+    slots: HashMap<String, Snapshot>,
+}
+
+/// Slots are kept in memory and mirrored to disk, `save` alone uses the
+/// `save.txt` that `part1` starts from.
+fn slot_path(slot: &str) -> String {
+    if slot.is_empty() {
+        "save.txt".to_string()
+    } else {
+        format!("save-{}.txt", slot)
+    }
 }
 
 enum Action {
@@ -24,8 +37,8 @@ enum Action {
     DropItem(String),
     Inv,
     Unknown(String),
-    Save,
-    Load,
+    Save(String),
+    Load(String),
 }
 
 impl From<&str> for Action {
@@ -37,8 +50,8 @@ impl From<&str> for Action {
             "e" => East,
             "w" => West,
             "i" => Inv,
-            "save" => Save,
-            "load" => Load,
+            s if s.starts_with("save") => Save(s.split_at(4).1.trim().to_string()),
+            l if l.starts_with("load") => Load(l.split_at(4).1.trim().to_string()),
             d if d.starts_with("d") => DropItem(d.split_at(1).1.to_string()),
             t if t.starts_with("t") => Action::Take(t.split_at(1).1.to_string()),
             a => Unknown(a.to_string()),
@@ -46,121 +59,95 @@ impl From<&str> for Action {
     }
 }
 
-impl Into<Vec<i64>> for Action {
-    fn into(self) -> Vec<i64> {
+impl From<Action> for String {
+    fn from(val: Action) -> Self {
         use Action::*;
 
-        match self {
+        match val {
             North => "north".to_string(),
             South => "south".to_string(),
             East => "east".to_string(),
             West => "west".to_string(),
             Take(value) => "take".to_string() + &value,
             DropItem(value) => "drop".to_string() + &value,
-            Inv | Save | Load => "inv".to_string(),
+            Inv | Save(_) | Load(_) => "inv".to_string(),
             Unknown(value) => value,
         }
-        .chars()
-        .map(|c| (c as u8) as i64)
-        .chain(iter::once(('\n' as u8) as i64))
-        .collect::<Vec<i64>>()
     }
 }
 
 impl Game<'_> {
     fn run(&mut self) {
-        while State::Halted != self.comp.state {
-            self.comp.run();
-            let output = self
-                .comp
-                .outputs
-                .iter()
-                .map(|&i| ((i as u8) as char).to_string())
-                .collect::<String>();
-            println!("{}", output);
-            self.comp.outputs.clear();
-            self.get_input().iter().for_each(|&i| self.comp.input(i));
+        while State::Halted != self.ascii.comp.state {
+            let text = self.ascii.read_text().expect("intcode program faulted");
+            println!("{}", text);
+            let line = self.get_input();
+            self.ascii.send_line(&line);
         }
     }
 
-    fn get_input(&mut self) -> Vec<i64> {
-        println!("What do you do? (n)orth, (s)outh, (e)ast, (w)est, (t)ake, (d)rop, (i)nv");
+    fn get_input(&mut self) -> String {
+        println!("What do you do? (n)orth, (s)outh, (e)ast, (w)est, (t)ake, (d)rop, (i)nv, save [slot], load [slot]");
         let mut input = String::new();
         stdin()
             .read_line(&mut input)
             .expect("error: unable to read user input");
         let action = Action::from(input.trim());
 
-        if let Action::Save = action {
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open("save.txt")
-                .expect("couldn't get file handle");
-            file.write(
-                self.comp
-                    .registers
-                    .iter()
-                    .map(|reg| reg.to_string())
-                    .collect::<Vec<_>>()
-                    .join(",")
-                    .as_bytes(),
-            )
-            .ok();
+        if let Action::Save(slot) = &action {
+            let snapshot = self.ascii.comp.snapshot();
+            if let Err(error) = snapshot.save(slot_path(slot)) {
+                println!("couldn't write {}: {}", slot_path(slot), error);
+            }
+            self.slots.insert(slot.clone(), snapshot);
         }
 
-        if let Action::Load = action {
-            let comp = Computer::new(load_registers("save.txt"));
-            self.comp = comp;
+        if let Action::Load(slot) = &action {
+            let snapshot = match self.slots.get(slot) {
+                Some(snapshot) => Ok(snapshot.clone()),
+                None => Snapshot::load(slot_path(slot)),
+            };
+            match snapshot {
+                Ok(snapshot) => self.ascii.comp.restore(snapshot),
+                Err(error) => println!("couldn't load {}: {}", slot_path(slot), error),
+            }
         }
         action.into()
     }
 
     fn auto(&mut self) {
-        fn input_cmd(action: Action, comp: &mut Computer) {
-            let instructions: Vec<i64> = action.into();
-            for instruction in instructions { comp.input(instruction) }
+        fn input_cmd(action: Action, ascii: &mut Ascii) {
+            ascii.send_line(&String::from(action));
+            ascii.read_text().expect("intcode program faulted");
         }
         print!("{:?}", self.inputs );
         //drop all the items in the input list
         for i in 0..self.inputs.len() {
             let action = Action::DropItem(" ".to_owned() + self.inputs.get(i).unwrap());
-            input_cmd(action, &mut self.comp);
-            self.comp.run();
+            input_cmd(action, &mut self.ascii);
         }
         // move north
-        input_cmd(Action::North, &mut self.comp);
-        self.comp.run();
-        input_cmd(Action::West, &mut self.comp);
-        self.comp.run();
-        input_cmd(Action::North, &mut self.comp);
-        self.comp.run();
-        input_cmd(Action::West, &mut self.comp);
-        self.comp.run();
-        self.comp.outputs.clear();
+        input_cmd(Action::North, &mut self.ascii);
+        input_cmd(Action::West, &mut self.ascii);
+        input_cmd(Action::North, &mut self.ascii);
+        input_cmd(Action::West, &mut self.ascii);
 
-        input_cmd(Action::North, &mut self.comp);
-        self.comp.run();
-
-        let output = self
-            .comp
-            .outputs
-            .iter()
-            .map(|&i| ((i as u8) as char).to_string())
-            .collect::<String>();
-        print!("{}", output);
-        self.comp.outputs.clear();
+        self.ascii.send_line(&String::from(Action::North));
+        let text = self.ascii.read_text().expect("intcode program faulted");
+        print!("{}", text);
     }
 }
 
-// fn part1() {
-//     let registers = input_to_registers();
-//     let comp = Computer::new(registers);
-//     let mut game = Game { comp, inputs: vec![] };
-//     game.run();
-// }
-//
+fn play() {
+    let registers = input_to_registers();
+    let mut game = Game {
+        ascii: Ascii::new(Computer::new(registers)),
+        inputs: vec![],
+        slots: HashMap::new(),
+    };
+    game.run();
+}
+
 fn part1() {
     let items = [
         "jam",
@@ -173,9 +160,12 @@ fn part1() {
         "manifold",];
     for size in 1..9  { 
         for list in items.iter().combinations(size) {
-            let registers = load_registers("save.txt");
-            let comp = Computer::new(registers);
-            let mut game = Game { comp, inputs: list.into_iter().map(|i| *i).collect::<Vec<&str>>() };
+            let snapshot = Snapshot::load(slot_path("")).expect("couldn't load save.txt");
+            let mut game = Game {
+                ascii: Ascii::new(Computer::from_snapshot(snapshot)),
+                inputs: list.into_iter().copied().collect::<Vec<&str>>(),
+                slots: HashMap::new(),
+            };
             game.auto();
         }
     }
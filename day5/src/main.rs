@@ -1,5 +1,4 @@
-use itertools::Itertools;
-use std::fs;
+use intcode::{input_to_registers, Computer, StdinSource};
 use std::io;
 
 fn main() {
@@ -51,22 +50,42 @@ impl From<u32> for Mode {
 }
 
 fn part1() {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let mut registers = input
-        .split(",")
-        .map(|x| match x.trim().parse::<i32>() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("could not parse {}", x);
-                panic!("could not parse");
-            }
-        })
-        .collect::<Vec<_>>();
+    let mut comp = Computer::new(input_to_registers()).with_io(
+        StdinSource::numbers().prompt("Please enter some input: "),
+        |output| println!("Output: {}", output),
+    );
+    comp.run().expect("intcode program faulted");
+}
 
-    run_registers(&mut registers);
+/// The interpreter this day was first solved with, kept to check the shared
+/// machine against.
+#[allow(dead_code)]
+fn run_registers(registers: &mut [i32]) {
+    run_registers_with(
+        registers,
+        || {
+            let mut input = String::new();
+            println!("Please enter some input: ");
+            io::stdin()
+                .read_line(&mut input)
+                .expect("Did not enter a correct string");
+            input
+                .trim()
+                .parse::<i32>()
+                .expect("Could not parse input must be i32")
+        },
+        |output| println!("Output: {}", output),
+    )
 }
 
-fn run_registers(registers: &mut Vec<i32>) {
+/// `run_registers` taking its inputs from `input` and handing its outputs
+/// to `output`.
+#[allow(dead_code)]
+fn run_registers_with<I, O>(registers: &mut [i32], mut input: I, mut output: O)
+where
+    I: FnMut() -> i32,
+    O: FnMut(i32),
+{
     let mut position = 0;
     loop {
         let machine_code = registers.get(position).unwrap().to_string();
@@ -103,21 +122,12 @@ fn run_registers(registers: &mut Vec<i32>) {
             }
             Opcode::Input => {
                 let deposit = get_mut_register_value(registers, pos.next());
-                let mut input = String::new();
-                println!("Please enter some input: ");
-                io::stdin()
-                    .read_line(&mut input)
-                    .expect("Did not enter a correct string");
-                let input_number = input
-                    .trim()
-                    .parse::<i32>()
-                    .expect("Could not parse input must be i32");
-                *deposit = input_number;
+                *deposit = input();
                 position = pos.next().unwrap();
             }
             Opcode::Output => {
                 let deposit = get_register_value(registers, pos.next(), opmodes.next());
-                println!("Output: {}", deposit);
+                output(deposit);
                 position = pos.next().unwrap();
             }
             Opcode::JumpIfTrue => {
@@ -159,7 +169,7 @@ fn run_registers(registers: &mut Vec<i32>) {
     }
 }
 
-fn get_register_value(registers: &Vec<i32>, index: Option<usize>, mode: Option<char>) -> i32 {
+fn get_register_value(registers: &[i32], index: Option<usize>, mode: Option<char>) -> i32 {
     match Mode::from(mode.unwrap_or('0').to_digit(10).unwrap()) {
         Mode::Position => *registers
             .get(
@@ -174,7 +184,7 @@ fn get_register_value(registers: &Vec<i32>, index: Option<usize>, mode: Option<c
     }
 }
 
-fn get_mut_register_value(registers: &mut Vec<i32>, index: Option<usize>) -> &mut i32 {
+fn get_mut_register_value(registers: &mut [i32], index: Option<usize>) -> &mut i32 {
     let d_index = *registers
         .get(index.unwrap())
         .expect("couldn't get deposit register position");
@@ -212,3 +222,26 @@ fn it_works_with_negatives() {
     run_registers(&mut registers);
     assert_eq!(registers, vec![1101, 100, -1, 4, 99]);
 }
+
+#[test]
+fn it_conforms() {
+    use intcode::{corpus, Dialect, Outcome};
+    use std::convert::TryFrom;
+
+    let report = Dialect::DAY5.check(&corpus(), |case| {
+        let to_i32 = |&value: &i64| i32::try_from(value).unwrap();
+        let mut registers = case.program.iter().map(to_i32).collect::<Vec<_>>();
+        let mut inputs = case.inputs.iter().map(to_i32);
+        let mut outputs = vec![];
+        run_registers_with(
+            &mut registers,
+            || inputs.next().expect("ran out of input"),
+            |output| outputs.push(i64::from(output)),
+        );
+        Outcome {
+            outputs,
+            memory: Some(registers.into_iter().map(i64::from).collect()),
+        }
+    });
+    assert!(report.disagreements.is_empty(), "{}", report);
+}
@@ -1,61 +1,44 @@
+use intcode::{input_to_registers, run_batch, Computer, State};
 use itertools::Itertools;
-use std::fs;
-use std::io;
-mod computer;
-use computer::Computer;
-use computer::State;
 
 fn main() {
     part2();
 }
 
+#[allow(dead_code)]
 fn part1() -> i64 {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let registers = input
-        .split(",")
-        .map(|x| match x.trim().parse::<i64>() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("could not parse {}", x);
-                panic!("could not parse");
-            }
-        })
-        .collect::<Vec<_>>();
+    let registers = input_to_registers();
 
     run_amplifier(&registers)
 }
 
 fn part2() -> i64 {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let registers = input
-        .split(",")
-        .map(|x| match x.trim().parse::<i64>() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("could not parse {}", x);
-                panic!("could not parse");
-            }
-        })
-        .collect::<Vec<_>>();
+    let registers = input_to_registers();
 
     run_feedback_amplifier(&registers)
 }
 
-fn run_amplifier(registers: &Vec<i64>) -> i64 {
-    let mut max = 0;
-    for phases in (0..=4).permutations(5) {
-        let mut last_result = 0;
-
-        for phase in &phases {
-            let mut computer = computer::Computer::new(registers.clone());
-            computer.input(*phase);
-            computer.input(last_result);
-            computer.run();
-            last_result = computer.outputs.pop_front().unwrap();
-        }
+fn run_amplifier(registers: &[i64]) -> i64 {
+    let permutations = (0..=4).permutations(5).collect::<Vec<_>>();
+    let computer = Computer::new(registers.to_vec());
+    // every permutation goes through each amplifier at the same time
+    let mut signals = vec![0; permutations.len()];
+    for amplifier in 0..5 {
+        let inputs = permutations
+            .iter()
+            .zip(&signals)
+            .map(|(phases, &signal)| vec![phases[amplifier], signal])
+            .collect::<Vec<_>>();
+        signals = run_batch(&computer, &inputs)
+            .into_iter()
+            .map(|outputs| outputs.expect("intcode program faulted")[0])
+            .collect();
+    }
 
-        if last_result > max {
-            max = last_result;
+    let mut max = 0;
+    for signal in signals {
+        if signal > max {
+            max = signal;
             println!("new max: {}", max);
         }
     }
@@ -64,16 +47,16 @@ fn run_amplifier(registers: &Vec<i64>) -> i64 {
     max
 }
 
-fn run_feedback_amplifier(registers: &Vec<i64>) -> i64 {
+fn run_feedback_amplifier(registers: &[i64]) -> i64 {
     let mut max = 0;
 
     for phases in (5..=9).permutations(5) {
         let mut comps = [
-            Computer::new(registers.clone()),
-            Computer::new(registers.clone()),
-            Computer::new(registers.clone()),
-            Computer::new(registers.clone()),
-            Computer::new(registers.clone()),
+            Computer::new(registers.to_vec()),
+            Computer::new(registers.to_vec()),
+            Computer::new(registers.to_vec()),
+            Computer::new(registers.to_vec()),
+            Computer::new(registers.to_vec()),
         ];
 
         for (i, phase) in phases.iter().enumerate() {
@@ -84,13 +67,11 @@ fn run_feedback_amplifier(registers: &Vec<i64>) -> i64 {
         let mut previous_output = 0;
         for i in (0..5).cycle() {
             let comp = &mut comps[i];
-            match comp.state {
-                State::Operating => {}
-                State::Waiting => {},
-                State::Halted => break,
+            if comp.state == State::Halted {
+                break;
             }
             comp.input(previous_output);
-            comp.run();
+            comp.run().expect("intcode program faulted");
             previous_output = comp.outputs.pop_front().unwrap();
         }
 
@@ -108,7 +89,7 @@ fn run_feedback_amplifier(registers: &Vec<i64>) -> i64 {
 
 #[test]
 fn it_works_out_the_max_thrust_signal() {
-    let mut registers = vec![
+    let registers = vec![
         3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
     ];
     assert_eq!(run_amplifier(&registers), 43210);
@@ -116,7 +97,7 @@ fn it_works_out_the_max_thrust_signal() {
 
 #[test]
 fn it_works_out_the_max_thrust_signal_example2() {
-    let mut registers = vec![
+    let registers = vec![
         3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4, 23, 99,
         0, 0,
     ];
@@ -125,7 +106,7 @@ fn it_works_out_the_max_thrust_signal_example2() {
 
 #[test]
 fn it_works_out_the_max_thrust_signal_example3() {
-    let mut registers = vec![
+    let registers = vec![
         3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33, 1, 33,
         31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
     ];
@@ -134,6 +115,6 @@ fn it_works_out_the_max_thrust_signal_example3() {
 
 #[test]
 fn it_works_with_feedback_amplifier() {
-    let mut registers = vec![3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5    ];
+    let registers = vec![3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5    ];
     assert_eq!(run_feedback_amplifier(&registers), 139629729);
 }
@@ -0,0 +1,252 @@
+//! Explores the day15 maze breadth first, cloning the droid's machine for
+//! every step like the day15 driver does, once on `Flat`, a machine with
+//! memory in one `Vec` as it was before pages, and once on `Computer`,
+//! whose clones share pages until they're written.
+//!
+//! `Flat` leaves out the checks `Computer` makes on every instruction, so
+//! the time spent cloning is what compares the two memories, the total
+//! time also compares the interpreters.
+//!
+//! Run with `cargo bench -p intcode --bench clone`.
+
+use intcode::{parse_program, Computer, Instruction, Mode, Opcode};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const DAY15: &str = include_str!("../../day15/input.txt");
+
+/// Keeps track of the bytes allocated now, at most and in total.
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+            TOTAL.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+struct Measure {
+    time: Duration,
+    cloning: Duration,
+    peak: usize,
+    total: usize,
+    tiles: usize,
+}
+
+/// The machine as it was before memory was paged: clones copy all of it.
+#[derive(Clone)]
+struct Flat {
+    registers: Vec<i64>,
+    decoded: Vec<Option<Instruction>>,
+    position: usize,
+    relative_base: i64,
+    inputs: VecDeque<i64>,
+    outputs: VecDeque<i64>,
+}
+
+impl Flat {
+    fn new(registers: Vec<i64>) -> Self {
+        Self {
+            registers,
+            decoded: vec![],
+            position: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+        }
+    }
+
+    fn decode(&mut self, address: usize) -> Instruction {
+        if let Some(&Some(instruction)) = self.decoded.get(address) {
+            return instruction;
+        }
+        let instruction = Instruction::decode(self.registers[address]).unwrap();
+        if address >= self.decoded.len() {
+            self.decoded.resize(address + 1, None);
+        }
+        self.decoded[address] = Some(instruction);
+        instruction
+    }
+
+    fn address(&self, param: usize, mode: Mode) -> usize {
+        let index = self.position + 1 + param;
+        match mode {
+            Mode::Position => self.registers[index] as usize,
+            Mode::Immediate => index,
+            Mode::Relative => (self.registers[index] + self.relative_base) as usize,
+        }
+    }
+
+    fn cell(&mut self, address: usize) -> &mut i64 {
+        if let Some(decoded) = self.decoded.get_mut(address) {
+            *decoded = None;
+        }
+        if address >= self.registers.len() {
+            self.registers.resize(address + 1, 0);
+        }
+        &mut self.registers[address]
+    }
+
+    fn value(&self, param: usize, mode: Mode) -> i64 {
+        let address = self.address(param, mode);
+        self.registers.get(address).copied().unwrap_or(0)
+    }
+
+    /// Runs until the machine halts or waits for input.
+    fn run(&mut self) {
+        loop {
+            let instruction = self.decode(self.position);
+            let modes = instruction.modes;
+            let a = |comp: &Self| comp.value(0, modes[0]);
+            let b = |comp: &Self| comp.value(1, modes[1]);
+            let mut next = self.position + 1 + instruction.opcode.arity();
+            match instruction.opcode {
+                Opcode::Add | Opcode::Mult | Opcode::LessThan | Opcode::EqualTo => {
+                    let (a, b) = (a(self), b(self));
+                    let value = match instruction.opcode {
+                        Opcode::Add => a.wrapping_add(b),
+                        Opcode::Mult => a.wrapping_mul(b),
+                        Opcode::LessThan => (a < b) as i64,
+                        _ => (a == b) as i64,
+                    };
+                    let address = self.address(2, modes[2]);
+                    *self.cell(address) = value;
+                }
+                Opcode::Input => match self.inputs.pop_front() {
+                    Some(input) => {
+                        let address = self.address(0, modes[0]);
+                        *self.cell(address) = input;
+                    }
+                    None => return,
+                },
+                Opcode::Output => {
+                    let value = a(self);
+                    self.outputs.push_back(value);
+                }
+                Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                    if (a(self) != 0) == (instruction.opcode == Opcode::JumpIfTrue) {
+                        next = b(self) as usize;
+                    }
+                }
+                Opcode::UpdateBase => self.relative_base += a(self),
+                Opcode::Halt => return,
+            }
+            self.position = next;
+        }
+    }
+}
+
+trait Droid: Clone {
+    fn load(program: &[i64]) -> Self;
+    /// Moves in `direction` and reports what's there.
+    fn step(&mut self, direction: i64) -> i64;
+}
+
+impl Droid for Flat {
+    fn load(program: &[i64]) -> Self {
+        Flat::new(program.to_vec())
+    }
+
+    fn step(&mut self, direction: i64) -> i64 {
+        self.inputs.push_back(direction);
+        self.run();
+        self.outputs.pop_front().unwrap()
+    }
+}
+
+impl Droid for Computer {
+    fn load(program: &[i64]) -> Self {
+        Computer::new(program.to_vec())
+    }
+
+    fn step(&mut self, direction: i64) -> i64 {
+        self.input(direction);
+        self.run().unwrap();
+        self.outputs.pop_front().unwrap()
+    }
+}
+
+/// Visits every open tile, returning how many there are and the time
+/// spent cloning.
+fn explore<D: Droid>(program: &[i64]) -> (usize, Duration) {
+    let mut seen = HashSet::new();
+    seen.insert((0, 0));
+    let mut queue = VecDeque::new();
+    queue.push_back((0, 0, D::load(program)));
+    let mut tiles = 1;
+    let mut cloning = Duration::default();
+    while let Some((x, y, droid)) = queue.pop_front() {
+        for &(direction, dx, dy) in &[(1, 0, 1), (2, 0, -1), (3, -1, 0), (4, 1, 0)] {
+            let (x, y) = (x + dx, y + dy);
+            if !seen.insert((x, y)) {
+                continue;
+            }
+            let start = Instant::now();
+            let mut droid = droid.clone();
+            cloning += start.elapsed();
+            if droid.step(direction) != 0 {
+                tiles += 1;
+                queue.push_back((x, y, droid));
+            }
+        }
+    }
+    (tiles, cloning)
+}
+
+fn measure<D: Droid>(program: &[i64]) -> Measure {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let total = TOTAL.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let (tiles, cloning) = explore::<D>(program);
+    Measure {
+        time: start.elapsed(),
+        cloning,
+        peak: PEAK.load(Ordering::Relaxed) - base,
+        total: TOTAL.load(Ordering::Relaxed) - total,
+        tiles,
+    }
+}
+
+fn main() {
+    let program = parse_program(DAY15).unwrap();
+    let flat = measure::<Flat>(&program);
+    let paged = measure::<Computer>(&program);
+    assert_eq!(flat.tiles, paged.tiles, "explorations differ");
+    for (name, result) in &[("flat", &flat), ("paged", &paged)] {
+        println!(
+            "{:<6} {:>10.2?}  cloning {:>10.2?}  peak {:>6} KiB  allocated {:>7} KiB  ({} tiles)",
+            name,
+            result.time,
+            result.cloning,
+            result.peak / 1024,
+            result.total / 1024,
+            result.tiles
+        );
+    }
+    println!(
+        "paged peaks {:.1}x lower, allocates {:.1}x less and clones {:.1}x faster",
+        flat.peak as f64 / paged.peak as f64,
+        flat.total as f64 / paged.total as f64,
+        flat.cloning.as_secs_f64() / paged.cloning.as_secs_f64()
+    );
+}
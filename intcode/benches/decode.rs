@@ -0,0 +1,172 @@
+//! Compares the decoded dispatch in `Computer` against the string based
+//! decoding it replaced, on the day19 beam probes and the day9 BOOST run.
+//!
+//! Run with `cargo bench -p intcode --bench decode`.
+
+use intcode::Computer;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const DAY9: &str = include_str!("../../day9/input.txt");
+const DAY19: &str = include_str!("../../day19/input.txt");
+
+fn parse(program: &str) -> Vec<i64> {
+    program
+        .split(',')
+        .map(|x| x.trim().parse::<i64>().unwrap())
+        .collect()
+}
+
+/// The interpreter as it was before instructions were decoded arithmetically.
+struct Legacy {
+    relative_base: i64,
+    position: usize,
+    inputs: VecDeque<i64>,
+    outputs: VecDeque<i64>,
+    registers: Vec<i64>,
+}
+
+impl Legacy {
+    fn new(registers: Vec<i64>) -> Self {
+        let mut preallocated_registers = registers;
+        preallocated_registers.extend_from_slice(&[0; 10_000]);
+        Self {
+            relative_base: 0,
+            position: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            registers: preallocated_registers,
+        }
+    }
+
+    fn run(&mut self) {
+        loop {
+            let machine_code = self.registers[self.position].to_string();
+            let mut opmodes = machine_code.chars().rev().skip(2);
+            let opcode = machine_code
+                .chars()
+                .rev()
+                .take(2)
+                .collect::<Vec<_>>()
+                .iter()
+                .rev()
+                .map(|c| c.to_string())
+                .collect::<String>()
+                .parse::<u8>()
+                .unwrap();
+            let mut pos = self.position + 1..;
+            match opcode {
+                1 | 2 | 7 | 8 => {
+                    let num1 = self.value(pos.next(), opmodes.next());
+                    let num2 = self.value(pos.next(), opmodes.next());
+                    let deposit = self.deposit(pos.next(), opmodes.next());
+                    self.registers[deposit] = match opcode {
+                        1 => num1 + num2,
+                        2 => num1 * num2,
+                        7 => (num1 < num2) as i64,
+                        _ => (num1 == num2) as i64,
+                    };
+                }
+                3 => match self.inputs.pop_front() {
+                    Some(input) => {
+                        let deposit = self.deposit(pos.next(), opmodes.next());
+                        self.registers[deposit] = input;
+                    }
+                    None => break,
+                },
+                4 => {
+                    let value = self.value(pos.next(), opmodes.next());
+                    self.outputs.push_back(value);
+                }
+                5 | 6 => {
+                    let num1 = self.value(pos.next(), opmodes.next());
+                    let num2 = self.value(pos.next(), opmodes.next());
+                    if (num1 != 0) == (opcode == 5) {
+                        self.position = num2 as usize;
+                        continue;
+                    }
+                }
+                9 => self.relative_base += self.value(pos.next(), opmodes.next()),
+                _ => break,
+            }
+            self.position = pos.next().unwrap();
+        }
+    }
+
+    fn deposit(&self, index: Option<usize>, mode: Option<char>) -> usize {
+        let value = self.registers[index.unwrap()];
+        match mode.unwrap_or('0') {
+            '2' => (value + self.relative_base) as usize,
+            _ => value as usize,
+        }
+    }
+
+    fn value(&self, index: Option<usize>, mode: Option<char>) -> i64 {
+        match mode.unwrap_or('0') {
+            '1' => self.registers[index.unwrap()],
+            _ => self.registers[self.deposit(index, mode)],
+        }
+    }
+}
+
+fn time<F: FnMut() -> i64>(mut f: F) -> (Duration, i64) {
+    let start = Instant::now();
+    let result = f();
+    (start.elapsed(), result)
+}
+
+fn report(name: &str, legacy: (Duration, i64), decoded: (Duration, i64)) {
+    assert_eq!(legacy.1, decoded.1, "{} results differ", name);
+    println!(
+        "{:<12} legacy {:>10.2?}  decoded {:>10.2?}  speed-up {:.1}x",
+        name,
+        legacy.0,
+        decoded.0,
+        legacy.0.as_secs_f64() / decoded.0.as_secs_f64()
+    );
+}
+
+fn main() {
+    let beam = parse(DAY19);
+    let probe_legacy = || {
+        let mut count = 0;
+        for x in 0..50 {
+            for y in 0..50 {
+                let mut comp = Legacy::new(beam.clone());
+                comp.inputs.extend(&[x, y]);
+                comp.run();
+                count += comp.outputs.pop_front().unwrap();
+            }
+        }
+        count
+    };
+    let probe_decoded = || {
+        let mut count = 0;
+        for x in 0..50 {
+            for y in 0..50 {
+                let mut comp = Computer::new(beam.clone());
+                comp.input(x);
+                comp.input(y);
+                comp.run().unwrap();
+                count += comp.outputs.pop_front().unwrap();
+            }
+        }
+        count
+    };
+    report("day19 probes", time(probe_legacy), time(probe_decoded));
+
+    let boost = parse(DAY9);
+    let boost_legacy = || {
+        let mut comp = Legacy::new(boost.clone());
+        comp.inputs.push_back(2);
+        comp.run();
+        comp.outputs.pop_front().unwrap()
+    };
+    let boost_decoded = || {
+        let mut comp = Computer::new(boost.clone());
+        comp.input(2);
+        comp.run().unwrap();
+        comp.outputs.pop_front().unwrap()
+    };
+    report("day9 boost", time(boost_legacy), time(boost_decoded));
+}
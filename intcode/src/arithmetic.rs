@@ -0,0 +1,119 @@
+//! What `Add` and `Mult` do with results that don't fit in an `i64`, see
+//! `Computer::set_arithmetic`.
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arithmetic {
+    /// Wraps around, the same in debug and release builds.
+    #[default]
+    Wrapping,
+    /// Faults with `ErrorKind::Overflow` on the instruction.
+    Checked,
+    /// Keeps results that outgrow 64 bits as big integers, see
+    /// `Memory::get_big`. Comparisons and jump conditions work on them,
+    /// anything else that reads one as an `i64`, an address or an output,
+    /// faults with `ErrorKind::BigValue`.
+    Big,
+}
+
+impl Arithmetic {
+    pub(crate) fn add(self, a: i64, b: i64) -> Result<i64, ErrorKind> {
+        match self {
+            Arithmetic::Checked => a.checked_add(b).ok_or(ErrorKind::Overflow),
+            _ => Ok(a.wrapping_add(b)),
+        }
+    }
+
+    pub(crate) fn mul(self, a: i64, b: i64) -> Result<i64, ErrorKind> {
+        match self {
+            Arithmetic::Checked => a.checked_mul(b).ok_or(ErrorKind::Overflow),
+            _ => Ok(a.wrapping_mul(b)),
+        }
+    }
+}
+
+#[test]
+fn it_agrees_with_the_corpus_under_every_policy() {
+    use crate::computer::Computer;
+    use crate::conformance::{corpus, Dialect, Outcome};
+
+    let cases = corpus();
+    for &arithmetic in &[Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Big] {
+        let report = Dialect::FULL.check(&cases, |case| {
+            let mut comp = Computer::new(case.program.clone());
+            comp.set_arithmetic(arithmetic);
+            for &input in &case.inputs {
+                comp.input(input);
+            }
+            comp.run().expect("intcode program faulted");
+            Outcome {
+                outputs: comp.outputs.iter().copied().collect(),
+                memory: Some(comp.registers.to_vec()),
+            }
+        });
+        assert!(
+            report.disagreements.is_empty(),
+            "{:?}: {}",
+            arithmetic,
+            report
+        );
+    }
+}
+
+#[test]
+fn it_overflows_by_policy() {
+    use crate::computer::{Computer, State};
+    use num_bigint::BigInt;
+
+    // MUL [5], [6] -> [7]
+    let program = vec![2, 5, 6, 7, 99, 1 << 62, 4, 0];
+    let run = |arithmetic| {
+        let mut comp = Computer::new(program.clone());
+        comp.set_arithmetic(arithmetic);
+        (comp.run(), comp)
+    };
+
+    let (state, comp) = run(Arithmetic::Wrapping);
+    assert_eq!(state, Ok(State::Halted));
+    assert_eq!(comp.registers.get(7), Ok(0));
+
+    let (state, comp) = run(Arithmetic::Checked);
+    let error = state.unwrap_err();
+    assert_eq!((error.kind, error.position), (ErrorKind::Overflow, 0));
+    assert_eq!(comp.position(), 0);
+
+    let (state, comp) = run(Arithmetic::Big);
+    assert_eq!(state, Ok(State::Halted));
+    assert_eq!(comp.registers.get_big(7), Ok(BigInt::from(1) << 64));
+    assert_eq!(comp.registers.get(7), Err(ErrorKind::BigValue(7)));
+}
+
+#[test]
+fn it_computes_past_64_bits() {
+    use crate::computer::Computer;
+    use crate::journal::Journal;
+    use crate::snapshot::Snapshot;
+    use num_bigint::BigInt;
+
+    // MUL [20], [21] -> [22]; MUL [22], [22] -> [23]; LT [22], [23] -> [24];
+    // JT [23], #17; HLT; OUT [23]; HLT
+    let big = 1 << 62;
+    let program = vec![
+        2, 20, 21, 22, 2, 22, 22, 23, 7, 22, 23, 24, 1005, 23, 17, 99, 0, 4, 23, 99, big, 4, 0, 0,
+        0,
+    ];
+    let mut comp = Computer::new(program.clone());
+    comp.set_arithmetic(Arithmetic::Big);
+    comp.set_journal(Journal::new(10));
+    let error = comp.run().unwrap_err();
+    assert_eq!((error.kind, error.position), (ErrorKind::BigValue(23), 17));
+    assert_eq!(comp.registers.get_big(23), Ok(BigInt::from(1) << 128));
+    assert_eq!(comp.registers.get(24), Ok(1));
+
+    let snapshot = comp.snapshot();
+    assert_eq!(snapshot.to_string().parse::<Snapshot>().unwrap(), snapshot);
+
+    assert_eq!(comp.rewind(4), 4);
+    assert_eq!(comp.registers, Computer::new(program).registers);
+}
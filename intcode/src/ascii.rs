@@ -0,0 +1,176 @@
+//! Talking to programs that speak ASCII, a line at a time.
+//!
+//! Outputs in `0..=127` are text, anything else is a number the program
+//! reports, usually once it has printed everything else.
+
+use crate::computer::{Computer, State};
+use crate::error::VmError;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// What a program printed: its text and the numbers it ended on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Text {
+    /// Everything before `numbers`, with any values outside ASCII in the
+    /// middle written out as numbers on their own line.
+    pub text: String,
+    /// The values outside ASCII the output ends with.
+    pub numbers: Vec<i64>,
+}
+
+fn is_ascii(value: i64) -> bool {
+    (0..=127).contains(&value)
+}
+
+impl Text {
+    pub fn from_outputs(values: &[i64]) -> Self {
+        let end = values
+            .iter()
+            .rposition(|&value| is_ascii(value))
+            .map_or(0, |i| i + 1);
+        let mut text = String::new();
+        for &value in &values[..end] {
+            if is_ascii(value) {
+                text.push(value as u8 as char);
+            } else {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text += &format!("{}\n", value);
+            }
+        }
+        Self {
+            text,
+            numbers: values[end..].to_vec(),
+        }
+    }
+}
+
+/// The text followed by one number per line.
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)?;
+        if !self.numbers.is_empty() && !self.text.is_empty() && !self.text.ends_with('\n') {
+            writeln!(f)?;
+        }
+        for number in &self.numbers {
+            writeln!(f, "{}", number)?;
+        }
+        Ok(())
+    }
+}
+
+/// A machine whose inputs and outputs are lines of text.
+pub struct Ascii {
+    pub comp: Computer,
+}
+
+impl Ascii {
+    pub fn new(comp: Computer) -> Self {
+        Self { comp }
+    }
+
+    /// Queues `line` and a newline as input.
+    pub fn send_line(&mut self, line: &str) {
+        for byte in line.bytes() {
+            self.comp.input(i64::from(byte));
+        }
+        self.comp.input(i64::from(b'\n'));
+    }
+
+    /// Runs until the program halts or waits for input and takes
+    /// everything it has output.
+    pub fn read_text(&mut self) -> Result<Text, VmError> {
+        self.comp.run()?;
+        let outputs = self.comp.outputs.drain(..).collect::<Vec<_>>();
+        Ok(Text::from_outputs(&outputs))
+    }
+
+    /// Runs until the output ends with `prompt`, or the program stops
+    /// first, and takes everything output so far.
+    pub fn read_until_prompt(&mut self, prompt: &str) -> Result<Text, VmError> {
+        let prompt = prompt.bytes().map(i64::from).collect::<Vec<_>>();
+        let mut outputs = self.comp.outputs.drain(..).collect::<Vec<_>>();
+        while !outputs.ends_with(&prompt) {
+            match self.comp.next_output()? {
+                Some(value) => outputs.push(value),
+                None => break,
+            }
+        }
+        Ok(Text::from_outputs(&outputs))
+    }
+
+    /// Bridges the program to a terminal: prints what it outputs and
+    /// feeds it a line from `input` whenever it waits, until it halts or
+    /// `input` runs out.
+    pub fn interact<R: BufRead, W: Write>(
+        &mut self,
+        mut input: R,
+        mut output: W,
+    ) -> Result<State, VmError> {
+        loop {
+            let text = self.read_text()?;
+            write!(output, "{}", text).ok();
+            output.flush().ok();
+            if self.comp.state != State::Waiting {
+                return Ok(self.comp.state.clone());
+            }
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                Ok(0) | Err(_) => return Ok(State::Waiting),
+                Ok(_) => self.send_line(line.trim_end_matches(&['\r', '\n'][..])),
+            }
+        }
+    }
+
+    /// `interact` on stdin and stdout.
+    pub fn terminal(&mut self) -> Result<State, VmError> {
+        self.interact(io::stdin().lock(), io::stdout())
+    }
+}
+
+#[test]
+fn it_separates_text_from_trailing_numbers() {
+    let text = Text::from_outputs(&[111, 107, 10, 1_000, 104, 105, 10, 19_360_000, -1]);
+    assert_eq!(text.text, "ok\n1000\nhi\n");
+    assert_eq!(text.numbers, vec![19_360_000, -1]);
+    assert_eq!(text.to_string(), "ok\n1000\nhi\n19360000\n-1\n");
+    assert_eq!(Text::from_outputs(&[42_000]).text, "");
+}
+
+#[test]
+fn it_talks_a_line_at_a_time() {
+    // prints "?", echoes a line back, then outputs its length times 1000
+    let program = crate::asm::assemble(
+        "
+        OUT #63
+        OUT #10
+    loop:
+        IN -> [char]
+        OUT [char]
+        EQ [char], #10 -> [done]
+        JT [done], #end
+        ADD [len], #1000 -> [len]
+        JT #1, #loop
+    end:
+        OUT [len]
+        HLT
+    char:  DATA 0
+    done:  DATA 0
+    len:   DATA 0
+    ",
+    )
+    .unwrap();
+    let mut ascii = Ascii::new(Computer::new(program.clone()));
+    assert_eq!(ascii.read_until_prompt("?\n").unwrap().text, "?\n");
+    ascii.send_line("hello");
+    let text = ascii.read_text().unwrap();
+    assert_eq!(text.text, "hello\n");
+    assert_eq!(text.numbers, vec![5000]);
+
+    let mut ascii = Ascii::new(Computer::new(program));
+    let mut out = vec![];
+    let state = ascii.interact(&b"hey\n"[..], &mut out).unwrap();
+    assert_eq!(state, State::Halted);
+    assert_eq!(String::from_utf8(out).unwrap(), "?\nhey\n3000\n");
+}
@@ -0,0 +1,407 @@
+//! A small assembler for Intcode.
+//!
+//! It reads the same syntax the disassembler writes, so a listing can be
+//! edited and assembled again:
+//!
+//! ```text
+//! start:  IN -> [n]            ; labels end with a colon
+//!         ADD [n], #-1 -> [n]  ; [x] position, #x immediate, [rb+x] relative
+//!         JT [n], #start
+//!         HLT
+//! n:      DATA 0
+//! ```
+//!
+//! Mnemonics are the short names from the listing or the `Opcode` variant
+//! names, in any case. Leading `0010:` addresses are ignored. `PUSH x`,
+//! `POP [x]`, `CALL #f` and `RET` expand to the usual relative base stack
+//! sequences.
+
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for AsmError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Operand {
+    mode: Mode,
+    value: Value,
+}
+
+impl Operand {
+    fn immediate(value: i64) -> Self {
+        Self {
+            mode: Mode::Immediate,
+            value: Value::Number(value),
+        }
+    }
+
+    fn relative(offset: i64) -> Self {
+        Self {
+            mode: Mode::Relative,
+            value: Value::Number(offset),
+        }
+    }
+}
+
+struct Fixup {
+    address: usize,
+    label: String,
+    offset: i64,
+    line: usize,
+}
+
+#[derive(Default)]
+struct Assembler {
+    words: Vec<i64>,
+    labels: HashMap<String, usize>,
+    fixups: Vec<Fixup>,
+    line: usize,
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn mnemonic(name: &str) -> Option<Opcode> {
+    Opcode::ALL.iter().copied().find(|opcode| {
+        opcode.mnemonic().eq_ignore_ascii_case(name)
+            || format!("{:?}", opcode).eq_ignore_ascii_case(name)
+    })
+}
+
+impl Assembler {
+    fn error<T>(&self, message: String) -> Result<T, AsmError> {
+        Err(AsmError {
+            line: self.line,
+            message,
+        })
+    }
+
+    fn value(&self, text: &str) -> Result<Value, AsmError> {
+        let text = text.trim();
+        if let Ok(number) = text.parse::<i64>() {
+            return Ok(Value::Number(number));
+        }
+        let (label, offset) = match text.find(['+', '-']) {
+            Some(split) => match text[split..].replace('+', "").trim().parse::<i64>() {
+                Ok(offset) => (text[..split].trim(), offset),
+                Err(_) => return self.error(format!("bad offset in `{}`", text)),
+            },
+            None => (text, 0),
+        };
+        if !is_label(label) {
+            return self.error(format!("expected a number or label, found `{}`", text));
+        }
+        Ok(Value::Label(label.to_string(), offset))
+    }
+
+    fn operand(&self, text: &str) -> Result<Operand, AsmError> {
+        let text = text.trim();
+        if let Some(value) = text.strip_prefix('#') {
+            return Ok(Operand {
+                mode: Mode::Immediate,
+                value: self.value(value)?,
+            });
+        }
+        let inner = match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Some(inner) => inner.trim(),
+            None => return self.error(format!("operand `{}` needs #, [..] or [rb..]", text)),
+        };
+        match inner.strip_prefix("rb") {
+            Some(offset) if offset.is_empty() || offset.starts_with(['+', '-']) => {
+                let offset = offset.trim();
+                let value = match offset.strip_prefix('+') {
+                    Some(offset) => self.value(offset)?,
+                    None if offset.is_empty() => Value::Number(0),
+                    None => self.value(offset)?,
+                };
+                Ok(Operand {
+                    mode: Mode::Relative,
+                    value,
+                })
+            }
+            _ => Ok(Operand {
+                mode: Mode::Position,
+                value: self.value(inner)?,
+            }),
+        }
+    }
+
+    fn operands(&self, text: &str) -> Result<Vec<Operand>, AsmError> {
+        text.replace("->", ",")
+            .split(',')
+            .map(str::trim)
+            .filter(|operand| !operand.is_empty())
+            .map(|operand| self.operand(operand))
+            .collect()
+    }
+
+    fn push(&mut self, value: Value) {
+        match value {
+            Value::Number(number) => self.words.push(number),
+            Value::Label(label, offset) => {
+                self.fixups.push(Fixup {
+                    address: self.words.len(),
+                    label,
+                    offset,
+                    line: self.line,
+                });
+                self.words.push(0);
+            }
+        }
+    }
+
+    fn emit(&mut self, opcode: Opcode, operands: Vec<Operand>) -> Result<(), AsmError> {
+        if operands.len() != opcode.arity() {
+            return self.error(format!(
+                "{} takes {} operands, found {}",
+                opcode.mnemonic(),
+                opcode.arity(),
+                operands.len()
+            ));
+        }
+        if let Some(write) = opcode.writes() {
+            if operands[write].mode == Mode::Immediate {
+                return self.error(format!("{} can't write to an immediate", opcode.mnemonic()));
+            }
+        }
+        let mut modes = [Mode::Position; 3];
+        for (mode, operand) in modes.iter_mut().zip(&operands) {
+            *mode = operand.mode;
+        }
+        self.words.push(Instruction { opcode, modes }.encode());
+        for operand in operands {
+            self.push(operand.value);
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, name: &str, rest: &str) -> Result<(), AsmError> {
+        if let Some(opcode) = mnemonic(name) {
+            let operands = self.operands(rest)?;
+            return self.emit(opcode, operands);
+        }
+        let name = name.to_ascii_uppercase();
+        if name == "DATA" {
+            for value in rest.split(',').filter(|value| !value.trim().is_empty()) {
+                let value = self.value(value)?;
+                self.push(value);
+            }
+            return Ok(());
+        }
+        let mut operands = self.operands(rest)?;
+        match name.as_str() {
+            "PUSH" if operands.len() == 1 => {
+                let value = operands.remove(0);
+                self.emit(
+                    Opcode::Add,
+                    vec![value, Operand::immediate(0), Operand::relative(0)],
+                )?;
+                self.emit(Opcode::UpdateBase, vec![Operand::immediate(1)])
+            }
+            "POP" if operands.len() == 1 => {
+                let destination = operands.remove(0);
+                self.emit(Opcode::UpdateBase, vec![Operand::immediate(-1)])?;
+                self.emit(
+                    Opcode::Add,
+                    vec![Operand::relative(0), Operand::immediate(0), destination],
+                )
+            }
+            "CALL" if operands.len() == 1 => {
+                let target = operands.remove(0);
+                let back = self.words.len() as i64 + 9;
+                self.emit(
+                    Opcode::Add,
+                    vec![
+                        Operand::immediate(back),
+                        Operand::immediate(0),
+                        Operand::relative(0),
+                    ],
+                )?;
+                self.emit(Opcode::UpdateBase, vec![Operand::immediate(1)])?;
+                self.emit(Opcode::JumpIfTrue, vec![Operand::immediate(1), target])
+            }
+            "RET" if operands.is_empty() => {
+                self.emit(Opcode::UpdateBase, vec![Operand::immediate(-1)])?;
+                self.emit(
+                    Opcode::JumpIfTrue,
+                    vec![Operand::immediate(1), Operand::relative(0)],
+                )
+            }
+            "PUSH" | "POP" | "CALL" | "RET" => {
+                self.error(format!("wrong number of operands for {}", name))
+            }
+            _ => self.error(format!("unknown mnemonic `{}`", name)),
+        }
+    }
+
+    fn assemble_line(&mut self, line: &str) -> Result<(), AsmError> {
+        let mut line = line.split(';').next().unwrap_or("").trim();
+
+        while let Some(colon) = line.find(':') {
+            let prefix = line[..colon].trim();
+            if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit()) {
+                // address column from a listing
+            } else if is_label(prefix) {
+                if self
+                    .labels
+                    .insert(prefix.to_string(), self.words.len())
+                    .is_some()
+                {
+                    return self.error(format!("label `{}` defined twice", prefix));
+                }
+            } else {
+                break;
+            }
+            line = line[colon + 1..].trim();
+        }
+
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (name, rest) = match line.find(char::is_whitespace) {
+            Some(split) => (&line[..split], &line[split..]),
+            None => (line, ""),
+        };
+        self.statement(name, rest)
+    }
+
+    fn finish(mut self) -> Result<Vec<i64>, AsmError> {
+        for fixup in &self.fixups {
+            match self.labels.get(&fixup.label) {
+                Some(&address) => self.words[fixup.address] = address as i64 + fixup.offset,
+                None => {
+                    return Err(AsmError {
+                        line: fixup.line,
+                        message: format!("undefined label `{}`", fixup.label),
+                    })
+                }
+            }
+        }
+        Ok(self.words)
+    }
+}
+
+/// Assembles `source` into a program ready for `Computer::new`.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut assembler = Assembler::default();
+    for (number, line) in source.lines().enumerate() {
+        assembler.line = number + 1;
+        assembler.assemble_line(line)?;
+    }
+    assembler.finish()
+}
+
+#[test]
+fn it_assembles_a_countdown() {
+    use crate::Computer;
+
+    let program = assemble(
+        "
+        start:  IN -> [n]            ; read the count
+        loop:   OUT [n]
+                ADD [n], #-1 -> [n]
+                JT [n], #loop
+                hlt
+        n:      DATA 0
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        program,
+        vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0]
+    );
+
+    let mut comp = Computer::new(program);
+    comp.input(3);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs, vec![3, 2, 1]);
+}
+
+#[test]
+fn it_accepts_variant_names_and_relative_operands() {
+    assert_eq!(
+        assemble("UpdateBase #5\nMult [rb-1], [rb], -> [rb+2]\nOutput [rb+label]\nlabel: Halt")
+            .unwrap(),
+        vec![109, 5, 22202, -1, 0, 2, 204, 8, 99]
+    );
+}
+
+#[test]
+fn it_expands_stack_helpers() {
+    use crate::Computer;
+
+    let program = assemble(
+        "
+                ARB #stack
+                PUSH #20
+                CALL #double
+                POP [result]
+                OUT [result]
+                HLT
+        double: ADD [rb-2], [rb-2] -> [rb-2]
+                RET
+        result: DATA 0
+        stack:  DATA 0
+        ",
+    )
+    .unwrap();
+    let mut comp = Computer::new(program);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs, vec![40]);
+}
+
+#[test]
+fn it_reports_errors_with_line_numbers() {
+    let error = assemble("HLT\nFOO #1").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert_eq!(assemble("ADD #1, #2 -> #3").unwrap_err().line, 1);
+    assert_eq!(
+        assemble("OUT [missing]").unwrap_err().message,
+        "undefined label `missing`"
+    );
+    assert!(assemble("a: HLT\na: HLT").is_err());
+    assert!(assemble("OUT 5").is_err());
+}
+
+#[test]
+fn it_round_trips_the_disassembler() {
+    use crate::disasm::listing;
+
+    for source in &[
+        include_str!("../../day9/input.txt"),
+        include_str!("../../day19/input.txt"),
+        include_str!("../../day25/input.txt"),
+    ] {
+        let program = source
+            .split(',')
+            .map(|x| x.trim().parse::<i64>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(assemble(&listing(&program)).unwrap(), program);
+    }
+}
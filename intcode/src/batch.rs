@@ -0,0 +1,126 @@
+//! Running one machine on many independent lists of inputs at once.
+//!
+//! Every run starts from its own clone of the same base machine, so runs
+//! can't see each other. A worker per core takes the next list off a
+//! shared counter until none are left, and results come back in the order
+//! their inputs were given whichever worker finished first.
+
+use crate::computer::{Computer, State};
+use crate::error::VmError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Runs a clone of `base` on each list of inputs, see `run_batch_with`.
+pub fn run_batch(base: &Computer, inputs: &[Vec<i64>]) -> Vec<Result<Vec<i64>, VmError>> {
+    run_batch_with(base, inputs, Computer::run)
+}
+
+/// Feeds each list of inputs to a clone of `base` and has `run` run it,
+/// e.g. with a translation's `run` in place of `Computer::run`.
+///
+/// Each result is everything that clone output by the time `run` returned,
+/// whether it halted or is waiting for more input, or the fault it hit.
+/// Outputs `base` hasn't collected yet aren't part of any result.
+pub fn run_batch_with<F>(
+    base: &Computer,
+    inputs: &[Vec<i64>],
+    run: F,
+) -> Vec<Result<Vec<i64>, VmError>>
+where
+    F: Fn(&mut Computer) -> Result<State, VmError> + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(inputs.len());
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = vec![];
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let values = match inputs.get(i) {
+                Some(values) => values,
+                None => return done,
+            };
+            let mut comp = base.clone();
+            comp.outputs.clear();
+            for &value in values {
+                comp.input(value);
+            }
+            let result = run(&mut comp).map(|_| comp.outputs.drain(..).collect());
+            done.push((i, result));
+        }
+    };
+
+    let mut results = (0..inputs.len()).map(|_| None).collect::<Vec<_>>();
+    thread::scope(|scope| {
+        let handles = (0..workers).map(|_| scope.spawn(work)).collect::<Vec<_>>();
+        for handle in handles {
+            for (i, result) in handle.join().expect("batch worker panicked") {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+#[test]
+fn it_keeps_results_in_input_order() {
+    // outputs three times each input until it reads a zero
+    let program = crate::asm::assemble(
+        "
+    loop:
+        IN -> [value]
+        JF [value], #end
+        MUL [value], #3 -> [value]
+        OUT [value]
+        JT #1, #loop
+    end:
+        HLT
+    value: DATA 0
+    ",
+    )
+    .unwrap();
+    let base = Computer::new(program);
+    let inputs = (0..200)
+        .map(|i| (1..=i % 7).chain(Some(0)).collect())
+        .collect::<Vec<Vec<i64>>>();
+    let results = run_batch(&base, &inputs);
+    assert_eq!(results.len(), inputs.len());
+    for (values, result) in inputs.iter().zip(results) {
+        let expected = values[..values.len() - 1]
+            .iter()
+            .map(|value| value * 3)
+            .collect::<Vec<_>>();
+        assert_eq!(result.unwrap(), expected);
+    }
+    assert!(run_batch(&base, &[]).is_empty());
+}
+
+#[test]
+fn it_reports_faults_run_by_run() {
+    use crate::error::ErrorKind;
+
+    // reads an address and outputs what's there
+    let base = Computer::new(vec![3, 3, 4, 0, 99]);
+    let inputs = vec![vec![0], vec![2], vec![-1], vec![4], vec![]];
+    let results = run_batch(&base, &inputs);
+    assert_eq!(results[0], Ok(vec![3]));
+    assert_eq!(results[1], Ok(vec![4]));
+    assert_eq!(
+        results[2].as_ref().unwrap_err().kind,
+        ErrorKind::NegativeAddress(-1)
+    );
+    assert_eq!(results[3], Ok(vec![99]));
+    assert_eq!(results[4], Ok(vec![]));
+}
+
+#[test]
+fn it_leaves_out_outputs_pending_on_the_base() {
+    // outputs 7, then echoes one input
+    let mut base = Computer::new(vec![104, 7, 3, 9, 4, 9, 99, 0, 0, 0]);
+    base.run().unwrap();
+    assert_eq!(base.outputs, vec![7]);
+    let results = run_batch(&base, &[vec![1], vec![2]]);
+    assert_eq!(results, vec![Ok(vec![1]), Ok(vec![2])]);
+    assert_eq!(base.outputs, vec![7]);
+}
@@ -0,0 +1,26 @@
+use intcode::{load_program, Computer, Debugger};
+use std::env;
+use std::io;
+use std::process;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: debug <program>");
+            process::exit(1);
+        }
+    };
+    let program = match load_program(&path) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    let mut debugger = Debugger::new(Computer::new(program));
+    let stdin = io::stdin();
+    debugger
+        .repl(stdin.lock(), io::stdout())
+        .expect("could not talk to the terminal");
+}
@@ -0,0 +1,21 @@
+use intcode::{listing, load_program};
+use std::env;
+use std::process;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: disasm <program|->");
+            process::exit(1);
+        }
+    };
+    let program = match load_program(&path) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    print!("{}", listing(&program));
+}
@@ -0,0 +1,42 @@
+use intcode::{load_program, Computer, StdinSource, StdoutSink};
+use std::env;
+use std::process;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let path = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: profile <program> [--ascii] [--csv]");
+            process::exit(1);
+        }
+    };
+    let ascii = args.iter().any(|arg| arg == "--ascii");
+    let csv = args.iter().any(|arg| arg == "--csv");
+
+    let program = match load_program(path) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    let mut comp = Computer::new(program);
+    comp.enable_profiler();
+    let (input, output) = if ascii {
+        (StdinSource::ascii(), StdoutSink::ascii())
+    } else {
+        (StdinSource::numbers(), StdoutSink::numbers())
+    };
+    let mut comp = comp.with_io(input, output);
+    if let Err(error) = comp.run() {
+        eprintln!("{}", error);
+    }
+
+    let profile = comp.profile().unwrap();
+    if csv {
+        eprint!("{}", profile.to_csv());
+    } else {
+        eprint!("{}", profile.report(20));
+    }
+}
@@ -0,0 +1,329 @@
+//! Basic blocks and the control flow between them, built on the code
+//! `find_code` discovers.
+//!
+//! Calls and returns are recognised by the pattern programs use for them:
+//! a call writes a constant return address through a relative parameter
+//! and then jumps unconditionally, a return jumps through a relative
+//! parameter.
+
+use crate::disasm::{disassemble, Line};
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::collections::BTreeSet;
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    /// Runs into the block starting at this address.
+    Fallthrough(usize),
+    /// Jumps to an immediate target, falling through to the next block
+    /// when the jump isn't taken.
+    Jump {
+        target: usize,
+        conditional: bool,
+    },
+    /// Jumps to `target` after pushing `returns_to` as a return address.
+    Call {
+        target: usize,
+        returns_to: usize,
+    },
+    /// Jumps to an address only known at run time after pushing
+    /// `returns_to`, such as a call through a function pointer.
+    IndirectCall {
+        returns_to: usize,
+    },
+    /// Jumps through a relative parameter without pushing anything first.
+    Return {
+        conditional: bool,
+    },
+    /// Jumps to an address only known at run time.
+    Indirect {
+        conditional: bool,
+    },
+    Halt,
+    /// Runs into something that doesn't decode as code.
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    /// The address just past the last instruction.
+    pub end: usize,
+    pub lines: Vec<Line>,
+    pub terminator: Terminator,
+}
+
+impl Block {
+    /// Addresses control can move to from this block, not counting where
+    /// a return goes.
+    pub fn successors(&self) -> Vec<usize> {
+        match self.terminator {
+            Terminator::Fallthrough(next) => vec![next],
+            Terminator::Jump {
+                target,
+                conditional,
+            } if conditional => vec![target, self.end],
+            Terminator::Jump { target, .. } | Terminator::Call { target, .. } => vec![target],
+            Terminator::Return { conditional } | Terminator::Indirect { conditional }
+                if conditional =>
+            {
+                vec![self.end]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// The constant an instruction computes from two immediates, if it does.
+fn constant(instruction: &Instruction, params: &[i64]) -> Option<i64> {
+    if instruction.modes[..2] != [Mode::Immediate; 2] {
+        return None;
+    }
+    match instruction.opcode {
+        Opcode::Add => Some(params[0].wrapping_add(params[1])),
+        Opcode::Mult => Some(params[0].wrapping_mul(params[1])),
+        _ => None,
+    }
+}
+
+fn is_jump(opcode: Opcode) -> bool {
+    opcode == Opcode::JumpIfTrue || opcode == Opcode::JumpIfFalse
+}
+
+fn terminator(
+    instruction: &Instruction,
+    params: &[i64],
+    end: usize,
+    returns_to: Option<usize>,
+) -> Terminator {
+    let opcode = instruction.opcode;
+    if opcode == Opcode::Halt {
+        return Terminator::Halt;
+    }
+    let conditional = instruction.modes[0] != Mode::Immediate;
+    let taken = (params[0] != 0) == (opcode == Opcode::JumpIfTrue);
+    if !conditional && !taken {
+        return Terminator::Fallthrough(end);
+    }
+    match instruction.modes[1] {
+        Mode::Immediate if params[1] >= 0 => {
+            let target = params[1] as usize;
+            match returns_to {
+                Some(returns_to) if !conditional => Terminator::Call { target, returns_to },
+                _ => Terminator::Jump {
+                    target,
+                    conditional,
+                },
+            }
+        }
+        _ => match returns_to {
+            Some(returns_to) if !conditional => Terminator::IndirectCall { returns_to },
+            _ if instruction.modes[1] == Mode::Relative => Terminator::Return { conditional },
+            _ => Terminator::Indirect { conditional },
+        },
+    }
+}
+
+/// Splits the code in `program` into basic blocks, in address order.
+///
+/// Blocks start at address 0, at immediate jump targets, after jumps and
+/// halts, and at constants that look like return addresses.
+pub fn basic_blocks(program: &[i64]) -> Vec<Block> {
+    let code = disassemble(program)
+        .into_iter()
+        .filter(|line| matches!(line, Line::Code { .. }))
+        .collect::<Vec<_>>();
+    let starts = code
+        .iter()
+        .map(|line| match line {
+            Line::Code { address, .. } => *address,
+            Line::Data { .. } => unreachable!(),
+        })
+        .collect::<BTreeSet<_>>();
+    let is_start = |value: i64| value >= 0 && starts.contains(&(value as usize));
+
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for line in &code {
+        if let Line::Code {
+            address,
+            instruction,
+            params,
+        } = line
+        {
+            let opcode = instruction.opcode;
+            if is_jump(opcode) || opcode == Opcode::Halt {
+                leaders.insert(address + 1 + opcode.arity());
+            }
+            if is_jump(opcode) && instruction.modes[1] == Mode::Immediate && is_start(params[1]) {
+                leaders.insert(params[1] as usize);
+            }
+            match constant(instruction, params) {
+                Some(value) if is_start(value) => {
+                    leaders.insert(value as usize);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut blocks: Vec<Block> = vec![];
+    let mut returns_to = None;
+    for line in code {
+        let (address, instruction, params) = match &line {
+            Line::Code {
+                address,
+                instruction,
+                params,
+            } => (*address, *instruction, params.clone()),
+            Line::Data { .. } => unreachable!(),
+        };
+        let end = address + 1 + params.len();
+        let continues = match blocks.last() {
+            Some(block) => block.end == address && block.terminator == Terminator::End,
+            None => false,
+        };
+        if !continues || leaders.contains(&address) {
+            if let Some(block) = blocks.last_mut() {
+                if block.end == address && block.terminator == Terminator::End {
+                    block.terminator = Terminator::Fallthrough(address);
+                }
+            }
+            returns_to = None;
+            blocks.push(Block {
+                start: address,
+                end,
+                lines: vec![],
+                terminator: Terminator::End,
+            });
+        }
+
+        let block = blocks.last_mut().unwrap();
+        block.end = end;
+        block.lines.push(line);
+        let opcode = instruction.opcode;
+        if is_jump(opcode) || opcode == Opcode::Halt {
+            block.terminator = terminator(&instruction, &params, end, returns_to);
+            if block.terminator == Terminator::Fallthrough(end) && !starts.contains(&end) {
+                block.terminator = Terminator::End;
+            }
+        } else if instruction.modes[2] == Mode::Relative {
+            if let Some(value) = constant(&instruction, &params) {
+                if is_start(value) {
+                    returns_to = Some(value as usize);
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// Renders the control flow graph of `program` in Graphviz DOT.
+///
+/// Conditional jumps that aren't taken are dashed, the edge from a call to
+/// where it returns is dotted. Blocks ending in a return are blue and
+/// blocks ending in any other indirect jump are red.
+pub fn cfg_dot(program: &[i64]) -> String {
+    let blocks = basic_blocks(program);
+    let mut dot = "digraph intcode {\n    node [shape=box, fontname=monospace];\n".to_string();
+    for block in &blocks {
+        let label = block
+            .lines
+            .iter()
+            .map(|line| format!("{}\\l", line))
+            .collect::<String>();
+        let color = match block.terminator {
+            Terminator::Return { .. } => ", color=blue",
+            Terminator::Indirect { .. } | Terminator::IndirectCall { .. } => ", color=red",
+            _ => "",
+        };
+        dot += &format!("    b{} [label=\"{}\"{}];\n", block.start, label, color);
+    }
+    for block in &blocks {
+        let from = block.start;
+        match block.terminator {
+            Terminator::Fallthrough(next) => dot += &format!("    b{} -> b{};\n", from, next),
+            Terminator::Jump {
+                target,
+                conditional,
+            } => {
+                dot += &format!("    b{} -> b{};\n", from, target);
+                if conditional {
+                    dot += &format!("    b{} -> b{} [style=dashed];\n", from, block.end);
+                }
+            }
+            Terminator::Call { target, returns_to } => {
+                dot += &format!("    b{} -> b{} [label=call];\n", from, target);
+                dot += &format!("    b{} -> b{} [style=dotted];\n", from, returns_to);
+            }
+            Terminator::IndirectCall { returns_to } => {
+                dot += &format!("    b{} -> b{} [style=dotted];\n", from, returns_to);
+            }
+            Terminator::Return { conditional } | Terminator::Indirect { conditional }
+                if conditional =>
+            {
+                dot += &format!("    b{} -> b{} [style=dashed];\n", from, block.end);
+            }
+            _ => {}
+        }
+    }
+    dot + "}\n"
+}
+
+#[test]
+fn it_splits_blocks_at_jumps_and_targets() {
+    // counts [13] down from 3, outputting each value
+    let program = [4, 13, 1001, 13, -1, 13, 1005, 13, 0, 104, -1, 99, 0, 3];
+    let blocks = basic_blocks(&program);
+    let spans = blocks
+        .iter()
+        .map(|block| (block.start, block.end, block.terminator))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        vec![
+            (
+                0,
+                9,
+                Terminator::Jump {
+                    target: 0,
+                    conditional: true
+                }
+            ),
+            (9, 12, Terminator::Halt),
+        ]
+    );
+    assert_eq!(blocks[0].successors(), vec![0, 9]);
+}
+
+#[test]
+fn it_recognises_calls_and_returns() {
+    let program = crate::asm::assemble(
+        "
+        ARB #100
+        CALL #double
+        OUT [rb+0]
+        HLT
+    double:
+        MUL [rb-1], #2 -> [rb-1]
+        RET
+    ",
+    )
+    .unwrap();
+    let blocks = basic_blocks(&program);
+    let call = blocks.iter().find(|block| block.start == 0).unwrap();
+    let double = blocks
+        .iter()
+        .find(|block| block.terminator == Terminator::Return { conditional: false })
+        .unwrap();
+    assert_eq!(
+        call.terminator,
+        Terminator::Call {
+            target: double.start,
+            returns_to: call.end
+        }
+    );
+    let dot = cfg_dot(&program);
+    assert!(dot.contains(&format!("b0 -> b{} [label=call]", double.start)));
+    assert!(dot.contains(&format!("b0 -> b{} [style=dotted]", call.end)));
+}
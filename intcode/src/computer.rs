@@ -0,0 +1,1186 @@
+use crate::arithmetic::Arithmetic;
+use crate::error::{ErrorKind, VmError};
+use crate::extension::{Call, Direction, Effect, InstructionSet};
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::io::{InputSource, OutputSink};
+use crate::journal::{Checkpoint, Entry, Journal};
+use crate::memory::{Limits, Memory};
+use crate::profile::{Access, Profiler};
+use crate::snapshot::Snapshot;
+use crate::trace::{Record, Tracer};
+use num_bigint::BigInt;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum State {
+    Operating,
+    Halted,
+    Waiting,
+    OutOfFuel,
+    Cancelled,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Event {
+    Executed,
+    Output(i64),
+    NeedsInput,
+    Halted,
+    OutOfFuel,
+    Cancelled,
+}
+
+/// An Intcode machine reading from `I` and writing to `O`, by default a
+/// pair of queues the caller fills and drains.
+#[derive(Clone)]
+pub struct Computer<I = VecDeque<i64>, O = VecDeque<i64>> {
+    relative_base: i64,
+    position: usize,
+    pub inputs: I,
+    pub outputs: O,
+    pub registers: Memory,
+    pub state: State,
+    fuel: Option<u64>,
+    cancel: Option<Arc<AtomicBool>>,
+    tracer: Option<Arc<Mutex<Tracer>>>,
+    profiler: Option<Arc<Mutex<Profiler>>>,
+    journal: Option<Journal>,
+    extensions: Option<Arc<InstructionSet>>,
+    arithmetic: Arithmetic,
+}
+
+impl Computer {
+    pub fn new(registers: Vec<i64>) -> Self {
+        Self::with_limits(registers, Limits::default())
+    }
+
+    pub fn with_limits(registers: Vec<i64>, limits: Limits) -> Self {
+        Self {
+            registers: Memory::with_limits(registers, limits),
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            position: 0,
+            state: State::Operating,
+            relative_base: 0,
+            fuel: None,
+            cancel: None,
+            tracer: None,
+            profiler: None,
+            journal: None,
+            extensions: None,
+            arithmetic: Arithmetic::Wrapping,
+        }
+    }
+
+    /// Captures the whole machine so it can be resumed later, see
+    /// `Snapshot::save` for writing it to disk.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers.clone(),
+            position: self.position,
+            relative_base: self.relative_base,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            state: self.state.clone(),
+            arithmetic: self.arithmetic,
+        }
+    }
+
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        let mut comp = Self::new(vec![]);
+        comp.restore(snapshot);
+        comp
+    }
+
+    /// Rewinds to `snapshot`, keeping this machine's fuel, cancel flag and
+    /// tracer. The journal starts over from here.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.clear();
+        }
+        self.registers = snapshot.registers;
+        self.position = snapshot.position;
+        self.relative_base = snapshot.relative_base;
+        self.inputs = snapshot.inputs;
+        self.outputs = snapshot.outputs;
+        self.state = snapshot.state;
+        self.arithmetic = snapshot.arithmetic;
+    }
+
+    /// Undoes up to `count` instructions, returning how many were undone.
+    ///
+    /// Needs a journal, see `set_journal`. Inputs the undone instructions
+    /// read go back on the front of `inputs`, outputs they produced are
+    /// taken off `outputs` if still queued.
+    pub fn rewind(&mut self, count: u64) -> u64 {
+        let steps = match &self.journal {
+            Some(journal) => journal.steps(),
+            None => return 0,
+        };
+        let target = steps
+            .saturating_sub(count)
+            .max(self.journal.as_ref().unwrap().earliest());
+        self.rewind_to(target);
+        steps - self.journal.as_ref().unwrap().steps()
+    }
+
+    /// Rewinds to just before the instruction that produced the `count`th
+    /// most recent output, so the next step produces it again. Returns how
+    /// many instructions were undone, `None` if the journal doesn't go back
+    /// that far.
+    pub fn rewind_outputs(&mut self, count: usize) -> Option<u64> {
+        let journal = self.journal.as_ref()?;
+        let steps = journal.steps();
+        let target = journal.output_step(count)?;
+        if target < journal.earliest() {
+            return None;
+        }
+        self.rewind_to(target);
+        Some(steps - target)
+    }
+
+    fn rewind_to(&mut self, target: u64) {
+        loop {
+            let journal = self.journal.as_mut().unwrap();
+            if journal.steps() <= target {
+                return;
+            }
+            if journal.entries.is_empty() {
+                break;
+            }
+            let entry = journal.pop().unwrap();
+            for &(address, value) in entry.writes.iter().rev() {
+                self.registers.set(address, value).ok();
+            }
+            if let Some((address, value)) = entry.big {
+                self.registers.set_big(address, value).ok();
+            }
+            self.position = entry.position;
+            self.relative_base = entry.relative_base;
+            self.state = State::Operating;
+            for &input in entry.inputs.iter().rev() {
+                self.inputs.push_front(input);
+            }
+            if entry.output.is_some() && self.outputs.back() == entry.output.as_ref() {
+                self.outputs.pop_back();
+            }
+        }
+
+        // past the entries, start over from a checkpoint and replay
+        let journal = self.journal.as_mut().unwrap();
+        let checkpoint = match journal.checkpoints.iter().rev().find(|c| c.step <= target) {
+            Some(checkpoint) => checkpoint.clone(),
+            None => return,
+        };
+        for &(_, output) in journal.outputs.iter().rev().filter(|&&(s, _)| s >= target) {
+            if self.outputs.back() == Some(&output) {
+                self.outputs.pop_back();
+            }
+        }
+        let mut inputs = journal
+            .inputs
+            .iter()
+            .filter(|&&(s, _)| s >= checkpoint.step)
+            .map(|&(_, input)| input)
+            .collect::<VecDeque<_>>();
+        inputs.extend(self.inputs.drain(..));
+        self.inputs = inputs;
+        journal.forget_from(checkpoint.step);
+        journal.entries.clear();
+        journal.steps = checkpoint.step;
+        self.registers = checkpoint.registers;
+        self.position = checkpoint.position;
+        self.relative_base = checkpoint.relative_base;
+        self.state = State::Operating;
+
+        let (fuel, cancel) = (self.fuel.take(), self.cancel.take());
+        let (tracer, profiler) = (self.tracer.take(), self.profiler.take());
+        while self.journal.as_ref().unwrap().steps() < target {
+            // outputs were queued the first time round
+            match self.step() {
+                Ok(Event::Executed) | Ok(Event::Output(_)) | Ok(Event::Halted) => {}
+                _ => break,
+            }
+        }
+        self.fuel = fuel;
+        self.cancel = cancel;
+        self.tracer = tracer;
+        self.profiler = profiler;
+    }
+}
+
+impl<O> Computer<VecDeque<i64>, O> {
+    pub fn input(&mut self, input: i64) {
+        self.inputs.push_back(input);
+    }
+}
+
+impl<I: InputSource, O: OutputSink> Computer<I, O> {
+    /// Moves the machine onto other I/O, dropping anything still queued on
+    /// the old one.
+    pub fn with_io<I2, O2>(self, inputs: I2, outputs: O2) -> Computer<I2, O2> {
+        Computer {
+            relative_base: self.relative_base,
+            position: self.position,
+            inputs,
+            outputs,
+            registers: self.registers,
+            state: self.state,
+            fuel: self.fuel,
+            cancel: self.cancel,
+            tracer: self.tracer,
+            profiler: self.profiler,
+            journal: self.journal,
+            extensions: self.extensions,
+            arithmetic: self.arithmetic,
+        }
+    }
+
+    /// Limits the machine to `fuel` more instructions, after which it stops
+    /// with `State::OutOfFuel` until topped up.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    /// Tops up the instruction budget, a machine without one stays unlimited.
+    pub fn add_fuel(&mut self, fuel: u64) {
+        if let Some(remaining) = self.fuel.as_mut() {
+            *remaining = remaining.saturating_add(fuel);
+        }
+    }
+
+    /// Instructions left in the budget, `None` when unlimited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Address of the next instruction to execute.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    /// Moves the instruction pointer, for code that runs the program outside
+    /// the interpreter and hands it back, such as `translate`d modules.
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    /// Whether fuel, a cancel flag, a tracer, a profiler, a journal or
+    /// arithmetic other than wrapping has to see every instruction, which
+    /// only the interpreter does.
+    pub fn needs_interpreter(&self) -> bool {
+        self.fuel.is_some()
+            || self.cancel.is_some()
+            || self.tracer.is_some()
+            || self.profiler.is_some()
+            || self.journal.is_some()
+            || self.arithmetic != Arithmetic::Wrapping
+    }
+
+    /// Picks what `Add` and `Mult` do on overflow, wrapping by default.
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Stops the machine with `State::Cancelled` once `flag` is raised. Clones
+    /// share the flag so one flag can stop a whole batch.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
+
+    /// Records every instruction executed from now on. Clones keep writing
+    /// to the same trace.
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(Arc::new(Mutex::new(tracer)));
+    }
+
+    /// Flushes what the tracer has written so far.
+    pub fn flush_trace(&self) -> io::Result<()> {
+        match &self.tracer {
+            Some(tracer) => tracer.lock().unwrap().flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts what every instruction from now on executes, reads and
+    /// writes. Clones count into the same profile, so a driver cloning a
+    /// base machine per query profiles all of them together.
+    pub fn enable_profiler(&mut self) {
+        let profiler = Profiler::new(self.registers.len());
+        self.profiler = Some(Arc::new(Mutex::new(profiler)));
+    }
+
+    /// Keeps an undo history from now on so the machine can be rewound,
+    /// see `Computer::rewind`. Unlike tracers, clones get their own copy.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Teaches the machine the user opcodes in `set`, see `InstructionSet`.
+    /// Clones share it.
+    pub fn set_instruction_set(&mut self, set: InstructionSet) {
+        self.extensions = Some(Arc::new(set));
+    }
+
+    /// What has been counted so far, `None` unless profiling.
+    pub fn profile(&self) -> Option<Profiler> {
+        Some(self.profiler.as_ref()?.lock().unwrap().clone())
+    }
+
+    /// Runs until the program halts or needs input it doesn't have yet.
+    ///
+    /// On a fault the instruction pointer is left on the faulting instruction.
+    pub fn run(&mut self) -> Result<State, VmError> {
+        loop {
+            match self.step()? {
+                Event::Executed => {}
+                Event::Output(value) => self.outputs.send_output(value),
+                _ => return Ok(self.state.clone()),
+            }
+        }
+    }
+
+    /// Executes a single instruction.
+    ///
+    /// Outputs are handed back in the event rather than queued on `outputs`.
+    pub fn step(&mut self) -> Result<Event, VmError> {
+        if let Some(cancel) = &self.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                self.state = State::Cancelled;
+                return Ok(Event::Cancelled);
+            }
+        }
+        if self.fuel == Some(0) {
+            self.state = State::OutOfFuel;
+            return Ok(Event::OutOfFuel);
+        }
+
+        let position = self.position;
+        let record = self.begin_record();
+        let access = self.begin_access();
+        let entry = self.begin_entry();
+        let event = self.execute().map_err(|kind| VmError {
+            position,
+            instruction: self.registers.get(position).unwrap_or(0),
+            kind,
+        })?;
+        if event != Event::NeedsInput && self.tracer.is_some() {
+            self.end_record(record, event);
+        }
+        if let (Some(access), Some(profiler)) = (access, &self.profiler) {
+            if event != Event::NeedsInput {
+                profiler.lock().unwrap().count(access);
+            }
+        }
+        if let (Some(entry), Some(journal)) = (entry, self.journal.as_mut()) {
+            if event != Event::NeedsInput {
+                journal.record(Self::end_entry(entry, &self.registers, event));
+            }
+        }
+        if event != Event::NeedsInput {
+            if let Some(fuel) = self.fuel.as_mut() {
+                *fuel -= 1;
+            }
+        }
+        self.state = match event {
+            Event::NeedsInput => State::Waiting,
+            Event::Halted => State::Halted,
+            _ => State::Operating,
+        };
+        Ok(event)
+    }
+
+    /// Steps until `stop` accepts an event or the program can't go on,
+    /// returning the event it stopped on. Outputs that don't stop the
+    /// run are queued on `outputs` as `run` would.
+    pub fn run_until<F>(&mut self, mut stop: F) -> Result<Event, VmError>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        loop {
+            let event = self.step()?;
+            if stop(&event) {
+                return Ok(event);
+            }
+            match event {
+                Event::Executed => {}
+                Event::Output(value) => self.outputs.send_output(value),
+                _ => return Ok(event),
+            }
+        }
+    }
+
+    /// Runs up to the next output, `None` if the program stops for any other
+    /// reason first.
+    pub fn next_output(&mut self) -> Result<Option<i64>, VmError> {
+        match self.run_until(|event| matches!(event, Event::Output(_)))? {
+            Event::Output(value) => Ok(Some(value)),
+            _ => Ok(None),
+        }
+    }
+
+    fn execute(&mut self) -> Result<Event, ErrorKind> {
+        let instruction = match self.registers.decode(self.position) {
+            Err(unknown @ ErrorKind::UnknownOpcode(_)) => return self.execute_extension(unknown),
+            decoded => decoded?,
+        };
+        let [mode1, mode2, mode3] = instruction.modes;
+        let pos = self.position;
+        if self.arithmetic == Arithmetic::Big {
+            if let Some(event) = self.execute_big(&instruction)? {
+                return Ok(event);
+            }
+        }
+        match instruction.opcode {
+            Opcode::Add => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                let sum = self.arithmetic.add(num1, num2)?;
+                *self.get_mut_register_value(pos + 3, mode3)? = sum;
+                self.position = pos + 4;
+            }
+            Opcode::Mult => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                let product = self.arithmetic.mul(num1, num2)?;
+                *self.get_mut_register_value(pos + 3, mode3)? = product;
+                self.position = pos + 4;
+            }
+            Opcode::Input => {
+                // fault before taking an input so it isn't lost
+                self.get_mut_register_value(pos + 1, mode1)?;
+                match self.inputs.next_input() {
+                    Some(input) => {
+                        *self.get_mut_register_value(pos + 1, mode1)? = input;
+                        self.position = pos + 2;
+                    }
+                    None => return Ok(Event::NeedsInput),
+                }
+            }
+            Opcode::Output => {
+                let deposit = self.get_register_value(pos + 1, mode1)?;
+                self.position = pos + 2;
+                return Ok(Event::Output(deposit));
+            }
+            Opcode::JumpIfTrue => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                if num1 != 0 {
+                    self.position = Self::to_address(num2)?;
+                } else {
+                    self.position = pos + 3;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                if num1 == 0 {
+                    self.position = Self::to_address(num2)?;
+                } else {
+                    self.position = pos + 3;
+                }
+            }
+            Opcode::LessThan => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                let deposit = self.get_mut_register_value(pos + 3, mode3)?;
+                *deposit = if num1 < num2 { 1 } else { 0 };
+                self.position = pos + 4;
+            }
+            Opcode::EqualTo => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                let deposit = self.get_mut_register_value(pos + 3, mode3)?;
+                *deposit = if num1 == num2 { 1 } else { 0 };
+                self.position = pos + 4;
+            }
+            Opcode::UpdateBase => {
+                let num1 = self.get_register_value(pos + 1, mode1)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(num1)
+                    .ok_or(ErrorKind::Overflow)?;
+                self.position = pos + 2;
+            }
+            Opcode::Halt => return Ok(Event::Halted),
+        }
+        Ok(Event::Executed)
+    }
+
+    /// Executes the instructions that read values as big integers under
+    /// `Arithmetic::Big`, `None` for the rest.
+    fn execute_big(&mut self, instruction: &Instruction) -> Result<Option<Event>, ErrorKind> {
+        let [mode1, mode2, mode3] = instruction.modes;
+        let pos = self.position;
+        let opcode = instruction.opcode;
+        match opcode {
+            Opcode::Add | Opcode::Mult | Opcode::LessThan | Opcode::EqualTo => {
+                let num1 = self.registers.get_big(self.address(pos + 1, mode1)?)?;
+                let num2 = self.registers.get_big(self.address(pos + 2, mode2)?)?;
+                if mode3 == Mode::Immediate {
+                    return Err(ErrorKind::ImmediateWrite);
+                }
+                let address = self.address(pos + 3, mode3)?;
+                let value = match opcode {
+                    Opcode::Add => num1 + num2,
+                    Opcode::Mult => num1 * num2,
+                    Opcode::LessThan => BigInt::from(i64::from(num1 < num2)),
+                    _ => BigInt::from(i64::from(num1 == num2)),
+                };
+                self.registers.set_big(address, value)?;
+                self.position = pos + 4;
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let num1 = self.registers.get_big(self.address(pos + 1, mode1)?)?;
+                let num2 = self.get_register_value(pos + 2, mode2)?;
+                if (num1 == BigInt::from(0)) == (opcode == Opcode::JumpIfFalse) {
+                    self.position = Self::to_address(num2)?;
+                } else {
+                    self.position = pos + 3;
+                }
+            }
+            _ => return Ok(None),
+        }
+        Ok(Some(Event::Executed))
+    }
+
+    /// Runs a user opcode, journaling it here since `begin_entry` only
+    /// knows the built-in ones.
+    fn execute_extension(&mut self, unknown: ErrorKind) -> Result<Event, ErrorKind> {
+        let pos = self.position;
+        let word = self.read(pos)?;
+        let opcode = match self.extensions.as_ref().and_then(|set| set.get(word)) {
+            Some(opcode) => opcode.clone(),
+            None => return Err(unknown),
+        };
+        let mut params = vec![];
+        let mut digits = word / 100;
+        for (i, &direction) in opcode.params.iter().enumerate() {
+            let mode = Mode::try_from((digits % 10) as u32).map_err(ErrorKind::UnknownMode)?;
+            digits /= 10;
+            params.push(match direction {
+                Direction::Read => self.get_register_value(pos + 1 + i, mode)?,
+                Direction::Write if mode == Mode::Immediate => {
+                    return Err(ErrorKind::ImmediateWrite)
+                }
+                Direction::Write => self.address(pos + 1 + i, mode)? as i64,
+            });
+        }
+
+        let relative_base = self.relative_base;
+        let mut call = Call::new(
+            pos,
+            params,
+            relative_base,
+            &mut self.registers,
+            &mut self.inputs,
+        );
+        let effect = match (opcode.handler)(&mut call) {
+            Ok(Effect::Wait) => {
+                call.undo();
+                return Ok(Event::NeedsInput);
+            }
+            Ok(effect) => effect,
+            Err(kind) => {
+                call.undo();
+                return Err(kind);
+            }
+        };
+        self.relative_base = call.relative_base();
+        let (writes, inputs) = (call.writes, call.taken);
+
+        let next = pos + 1 + opcode.params.len();
+        let event = match effect {
+            Effect::Next => {
+                self.position = next;
+                Event::Executed
+            }
+            Effect::Jump(target) => {
+                self.position = target;
+                Event::Executed
+            }
+            Effect::Output(value) => {
+                self.position = next;
+                Event::Output(value)
+            }
+            Effect::Halt => Event::Halted,
+            Effect::Wait => unreachable!(),
+        };
+        if let Some(journal) = self.journal.as_mut() {
+            let output = match event {
+                Event::Output(value) => Some(value),
+                _ => None,
+            };
+            journal.record(Entry {
+                position: pos,
+                relative_base,
+                writes,
+                inputs,
+                output,
+                big: None,
+            });
+        }
+        Ok(event)
+    }
+
+    /// Captures what the tracer needs from before the instruction runs, if
+    /// it wants this one at all.
+    fn begin_record(&mut self) -> Option<Record> {
+        let tracer = self.tracer.clone()?;
+        let instruction = self.registers.decode(self.position).ok()?;
+        let opcode = instruction.opcode;
+        if !tracer.lock().unwrap().wants(self.position, opcode) {
+            return None;
+        }
+
+        let mut operands = vec![];
+        for (i, &mode) in instruction.modes.iter().take(opcode.arity()).enumerate() {
+            let index = self.position + 1 + i;
+            let operand = if opcode.writes() == Some(i) {
+                self.address(index, mode).ok()? as i64
+            } else {
+                self.get_register_value(index, mode).ok()?
+            };
+            operands.push(operand);
+        }
+        Some(Record {
+            step: 0,
+            ip: self.position,
+            opcode,
+            operands,
+            write: None,
+            relative_base: Some(self.relative_base),
+            input: None,
+            output: None,
+        })
+    }
+
+    /// The addresses the next instruction reads and writes, if profiling.
+    fn begin_access(&mut self) -> Option<Access> {
+        self.profiler.as_ref()?;
+        let instruction = self.registers.decode(self.position).ok()?;
+        let opcode = instruction.opcode;
+        let mut reads = vec![];
+        let mut write = None;
+        for (i, &mode) in instruction.modes.iter().take(opcode.arity()).enumerate() {
+            let address = self.address(self.position + 1 + i, mode).ok()?;
+            if opcode.writes() == Some(i) {
+                write = Some(address);
+            } else if mode != Mode::Immediate {
+                reads.push(address);
+            }
+        }
+        Some(Access {
+            ip: self.position,
+            opcode,
+            reads,
+            write,
+        })
+    }
+
+    /// Takes a checkpoint if one is due and notes what the next
+    /// instruction is about to overwrite, if journaling.
+    fn begin_entry(&mut self) -> Option<Entry> {
+        let journal = self.journal.as_ref()?;
+        if journal.wants_checkpoint() {
+            let checkpoint = Checkpoint {
+                step: journal.steps(),
+                registers: self.registers.clone(),
+                position: self.position,
+                relative_base: self.relative_base,
+            };
+            self.journal.as_mut()?.checkpoint(checkpoint);
+        }
+        let instruction = self.registers.decode(self.position).ok()?;
+        let mut big = None;
+        let writes = match instruction.opcode.writes() {
+            Some(i) => {
+                let index = self.position + 1 + i;
+                let address = self.address(index, instruction.modes[i]).ok()?;
+                match self.registers.get(address) {
+                    Ok(old) => vec![(address, old)],
+                    Err(ErrorKind::BigValue(_)) => {
+                        big = Some((address, self.registers.get_big(address).ok()?));
+                        vec![(address, 0)]
+                    }
+                    Err(_) => return None,
+                }
+            }
+            None => vec![],
+        };
+        // filled in with the value read once it's executed
+        let inputs = match instruction.opcode {
+            Opcode::Input => vec![0],
+            _ => vec![],
+        };
+        Some(Entry {
+            position: self.position,
+            relative_base: self.relative_base,
+            writes,
+            inputs,
+            output: None,
+            big,
+        })
+    }
+
+    fn end_entry(mut entry: Entry, registers: &Memory, event: Event) -> Entry {
+        if !entry.inputs.is_empty() {
+            let (address, _) = entry.writes[0];
+            entry.inputs = registers.get(address).ok().into_iter().collect();
+        }
+        if let Event::Output(value) = event {
+            entry.output = Some(value);
+        }
+        entry
+    }
+
+    fn end_record(&mut self, record: Option<Record>, event: Event) {
+        let mut tracer = match &self.tracer {
+            Some(tracer) => tracer.lock().unwrap(),
+            None => return,
+        };
+        let mut record = match record {
+            Some(record) => record,
+            None => return tracer.skip(),
+        };
+        if let Some(i) = record.opcode.writes() {
+            let address = record.operands[i] as usize;
+            record.write = Some((address, self.registers.get(address).unwrap_or(0)));
+        }
+        if record.opcode == Opcode::Input {
+            record.input = record.write.map(|(_, value)| value);
+        }
+        record.relative_base = match record.relative_base {
+            Some(base) if base != self.relative_base => Some(self.relative_base),
+            _ => None,
+        };
+        if let Event::Output(value) = event {
+            record.output = Some(value);
+        }
+        tracer.record(record);
+    }
+
+    fn to_address(value: i64) -> Result<usize, ErrorKind> {
+        if value < 0 {
+            Err(ErrorKind::NegativeAddress(value))
+        } else {
+            Ok(value as usize)
+        }
+    }
+
+    fn read(&self, address: usize) -> Result<i64, ErrorKind> {
+        self.registers.get(address)
+    }
+
+    fn address(&self, index: usize, mode: Mode) -> Result<usize, ErrorKind> {
+        match mode {
+            Mode::Position => Self::to_address(self.read(index)?),
+            Mode::Immediate => Ok(index),
+            Mode::Relative => Self::to_address(
+                self.read(index)?
+                    .checked_add(self.relative_base)
+                    .ok_or(ErrorKind::Overflow)?,
+            ),
+        }
+    }
+
+    fn get_register_value(&self, index: usize, mode: Mode) -> Result<i64, ErrorKind> {
+        let address = self.address(index, mode)?;
+        self.read(address)
+    }
+
+    fn get_mut_register_value(&mut self, index: usize, mode: Mode) -> Result<&mut i64, ErrorKind> {
+        if mode == Mode::Immediate {
+            return Err(ErrorKind::ImmediateWrite);
+        }
+        let address = self.address(index, mode)?;
+        self.registers.get_mut(address)
+    }
+}
+
+#[test]
+fn it_works() {
+    let mut comp = Computer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+    comp.run().unwrap();
+    assert_eq!(
+        comp.registers.to_vec(),
+        [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
+    );
+}
+
+#[test]
+fn it_works2() {
+    let mut comp = Computer::new(vec![1, 0, 0, 0, 99]);
+    comp.run().unwrap();
+    assert_eq!(comp.registers.to_vec(), [2, 0, 0, 0, 99]);
+}
+
+#[test]
+fn it_works_with_modes() {
+    let mut comp = Computer::new(vec![1002, 4, 3, 4, 33]);
+    comp.run().unwrap();
+    assert_eq!(comp.registers.to_vec(), [1002, 4, 3, 4, 99]);
+}
+
+#[test]
+fn it_works_with_negatives() {
+    let mut comp = Computer::new(vec![1101, 100, -1, 4, 0]);
+    comp.run().unwrap();
+    assert_eq!(comp.registers.to_vec(), [1101, 100, -1, 4, 99]);
+}
+
+#[test]
+fn it_works_with_relative_base() {
+    let mut comp = Computer::new(vec![
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ]);
+    comp.run().unwrap();
+    assert_eq!(
+        comp.outputs,
+        vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]
+    );
+}
+
+#[test]
+fn it_works_with_relative_base_example2() {
+    let mut comp = Computer::new(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0]);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs.pop_front(), Some(1219070632396864));
+}
+
+#[test]
+fn it_works_with_relative_base_example3() {
+    let mut comp = Computer::new(vec![104, 1125899906842624, 99]);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs.pop_front(), Some(1125899906842624));
+}
+
+#[test]
+fn it_reports_relative_base_overflow() {
+    let mut comp = Computer::new(vec![109, i64::MAX, 109, 1, 99]);
+    let error = comp.run().unwrap_err();
+    assert_eq!(error.position, 2);
+    assert_eq!(error.kind, ErrorKind::Overflow);
+    assert_eq!(comp.relative_base(), i64::MAX);
+
+    // relative addresses past the end of i64
+    let mut comp = Computer::new(vec![109, i64::MAX, 204, 1, 99]);
+    assert_eq!(comp.run().unwrap_err().kind, ErrorKind::Overflow);
+}
+
+#[test]
+fn it_reports_unknown_opcodes() {
+    let mut comp = Computer::new(vec![1, 0, 0, 0, 42]);
+    let error = comp.run().unwrap_err();
+    assert_eq!(error.position, 4);
+    assert_eq!(error.instruction, 42);
+    assert_eq!(error.kind, ErrorKind::UnknownOpcode(42));
+}
+
+#[test]
+fn it_reports_unknown_modes() {
+    let mut comp = Computer::new(vec![301, 0, 0, 0, 99]);
+    let error = comp.run().unwrap_err();
+    assert_eq!(error.position, 0);
+    assert_eq!(error.kind, ErrorKind::UnknownMode(3));
+}
+
+#[test]
+fn it_reports_negative_addresses() {
+    let mut comp = Computer::new(vec![1, -1, 0, 0, 99]);
+    assert_eq!(comp.run().unwrap_err().kind, ErrorKind::NegativeAddress(-1));
+
+    let mut comp = Computer::new(vec![109, -5, 204, 0, 99]);
+    let error = comp.run().unwrap_err();
+    assert_eq!(error.position, 2);
+    assert_eq!(error.instruction, 204);
+    assert_eq!(error.kind, ErrorKind::NegativeAddress(-5));
+}
+
+#[test]
+fn it_reports_writes_through_immediate_mode() {
+    let mut comp = Computer::new(vec![11101, 1, 1, 0, 99]);
+    assert_eq!(comp.run().unwrap_err().kind, ErrorKind::ImmediateWrite);
+}
+
+#[test]
+fn it_reads_and_writes_far_past_the_program() {
+    let mut comp = Computer::new(vec![1101, 7, 8, 1_000_000, 4, 1_000_000, 99]);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs.pop_front(), Some(15));
+    assert_eq!(comp.registers.len(), 1_000_001);
+}
+
+#[test]
+fn it_reports_memory_limits() {
+    let limits = Limits {
+        dense: 64,
+        sparse_cells: 0,
+        max_address: 1000,
+    };
+    let mut comp = Computer::with_limits(vec![1101, 1, 1, 500, 99], limits);
+    assert_eq!(
+        comp.run().unwrap_err().kind,
+        ErrorKind::MemoryExhausted(500)
+    );
+
+    let mut comp = Computer::with_limits(vec![4, 2000, 99], limits);
+    assert_eq!(comp.run().unwrap_err().kind, ErrorKind::OutOfBounds(2000));
+}
+
+#[test]
+fn it_runs_self_modifying_code() {
+    // outputs, rewrites its own first instruction to position mode and loops once
+    let mut comp = Computer::new(vec![
+        104, 7, 1005, 20, 19, 1101, 1, 0, 20, 1101, 4, 0, 0, 1105, 1, 0, 99, 99, 99, 99, 0,
+    ]);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs, vec![7, 0]);
+}
+
+#[test]
+fn it_steps_one_instruction_at_a_time() {
+    let mut comp = Computer::new(vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0]);
+    assert_eq!(comp.step(), Ok(Event::NeedsInput));
+    assert_eq!(comp.state, State::Waiting);
+    comp.input(41);
+    assert_eq!(comp.step(), Ok(Event::Executed));
+    assert_eq!(comp.step(), Ok(Event::Executed));
+    assert_eq!(comp.step(), Ok(Event::Output(42)));
+    assert!(comp.outputs.is_empty());
+    assert_eq!(comp.step(), Ok(Event::Halted));
+    assert_eq!(comp.state, State::Halted);
+}
+
+#[test]
+fn it_runs_until_the_next_output() {
+    let mut comp = Computer::new(vec![104, 1, 104, 2, 104, 3, 99]);
+    assert_eq!(comp.next_output(), Ok(Some(1)));
+    assert_eq!(
+        comp.run_until(|event| *event == Event::Output(3)),
+        Ok(Event::Output(3))
+    );
+    assert_eq!(comp.outputs, vec![2]);
+    assert_eq!(comp.next_output(), Ok(None));
+}
+
+#[test]
+fn it_runs_out_of_fuel_and_resumes() {
+    // counts [20] up to 3 then outputs it
+    let mut comp = Computer::new(vec![
+        1101, 0, 0, 20, 1001, 20, 1, 20, 1007, 20, 3, 21, 1005, 21, 4, 4, 20, 99, 0, 0, 0, 0,
+    ]);
+    comp.set_fuel(3);
+    assert_eq!(comp.run(), Ok(State::OutOfFuel));
+    assert_eq!(comp.fuel(), Some(0));
+    assert!(comp.outputs.is_empty());
+
+    comp.add_fuel(100);
+    assert_eq!(comp.run(), Ok(State::Halted));
+    assert_eq!(comp.outputs, vec![3]);
+}
+
+#[test]
+fn it_stops_when_cancelled() {
+    let mut comp = Computer::new(vec![1105, 1, 0]);
+    let cancel = Arc::new(AtomicBool::new(false));
+    comp.set_cancel_flag(cancel.clone());
+    comp.set_fuel(1000);
+    assert_eq!(comp.run(), Ok(State::OutOfFuel));
+
+    cancel.store(true, Ordering::Relaxed);
+    comp.add_fuel(1000);
+    assert_eq!(comp.run(), Ok(State::Cancelled));
+    assert_eq!(comp.fuel(), Some(1000));
+}
+
+#[test]
+fn it_saturates_topped_up_fuel() {
+    let mut comp = Computer::new(vec![99]);
+    comp.set_fuel(u64::MAX);
+    comp.add_fuel(1);
+    assert_eq!(comp.fuel(), Some(u64::MAX));
+}
+
+#[test]
+fn it_traces_executed_instructions() {
+    use std::io::Write;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let out = Shared::default();
+    let mut comp = Computer::new(vec![3, 11, 109, 4, 22101, 1, 7, 0, 204, 0, 99]);
+    comp.set_tracer(Tracer::new(out.clone()));
+    assert_eq!(comp.run(), Ok(State::Waiting));
+    comp.input(41);
+    comp.run().unwrap();
+    comp.flush_trace().unwrap();
+
+    let trace = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(
+        trace.lines().collect::<Vec<_>>(),
+        vec![
+            r#"{"step":0,"ip":0,"opcode":"IN","operands":[11],"write":{"address":11,"value":41},"input":41}"#,
+            r#"{"step":1,"ip":2,"opcode":"ARB","operands":[4],"relative_base":4}"#,
+            r#"{"step":2,"ip":4,"opcode":"ADD","operands":[1,41,4],"write":{"address":4,"value":42}}"#,
+            r#"{"step":3,"ip":8,"opcode":"OUT","operands":[42],"output":42}"#,
+            r#"{"step":4,"ip":10,"opcode":"HLT","operands":[]}"#,
+        ]
+    );
+}
+
+#[test]
+fn it_resumes_from_a_snapshot() {
+    // adds pairs of inputs until it reads a zero
+    let program = vec![
+        3, 100, 1006, 100, 14, 3, 101, 1, 100, 101, 102, 4, 102, 1105, 1, 0, 99,
+    ];
+    let mut comp = Computer::new(program);
+    comp.input(2);
+    assert_eq!(comp.run(), Ok(State::Waiting));
+    let saved = comp.snapshot();
+
+    comp.input(5);
+    comp.run().unwrap();
+    assert_eq!(comp.outputs, vec![7]);
+
+    let mut resumed = Computer::from_snapshot(saved.to_string().parse().unwrap());
+    assert_eq!(resumed.position(), 5);
+    resumed.input(40);
+    resumed.run().unwrap();
+    assert_eq!(resumed.outputs, vec![42]);
+}
+
+#[test]
+fn it_runs_on_closures_and_channels() {
+    use std::sync::mpsc;
+
+    // doubles each input until it reads a zero
+    let program = vec![3, 20, 1006, 20, 14, 1002, 20, 2, 20, 4, 20, 1105, 1, 0, 99];
+
+    let mut fed = vec![3, 5, 0].into_iter();
+    let mut seen = vec![];
+    let mut comp = Computer::new(program.clone()).with_io(|| fed.next(), |v| seen.push(v));
+    assert_eq!(comp.run(), Ok(State::Halted));
+    drop(comp);
+    assert_eq!(seen, vec![6, 10]);
+
+    let (send_input, inputs) = mpsc::channel();
+    let (outputs, receive_output) = mpsc::channel();
+    let mut comp = Computer::new(program).with_io(inputs, outputs);
+    send_input.send(21).unwrap();
+    drop(send_input);
+    assert_eq!(comp.run(), Ok(State::Waiting));
+    assert_eq!(receive_output.try_iter().collect::<Vec<_>>(), vec![42]);
+}
+
+#[test]
+fn it_profiles_across_clones() {
+    let mut base = Computer::new(vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0]);
+    base.enable_profiler();
+    base.run().unwrap();
+    assert_eq!(base.profile().unwrap().instructions(), 0);
+
+    for input in 0..2 {
+        let mut comp = base.clone();
+        comp.input(input);
+        comp.run().unwrap();
+    }
+    let profile = base.profile().unwrap();
+    assert_eq!(profile.instructions(), 8);
+    assert_eq!(profile.instructions_per_input(), Some(4.0));
+    assert_eq!(profile.executions(2), 2);
+    assert_eq!((profile.reads(9), profile.writes(9)), (4, 4));
+    assert_eq!(profile.coverage(), (9, 10));
+}
+
+#[test]
+fn it_rewinds_instructions_and_outputs() {
+    // counts 3 down to 1 through cell 12
+    let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+    let mut history = vec![];
+    let mut comp = Computer::new(program.clone());
+    comp.input(3);
+    while comp.state != State::Halted {
+        history.push(comp.snapshot());
+        comp.step().unwrap();
+    }
+
+    for journal in [Journal::new(100), Journal::new(2).checkpoints(3, 10)].iter() {
+        let mut comp = Computer::new(program.clone());
+        comp.set_journal(journal.clone());
+        comp.input(3);
+        comp.run().unwrap();
+        assert_eq!(comp.outputs, vec![3, 2, 1]);
+
+        assert_eq!(comp.rewind(4), 4);
+        let expected = &history[history.len() - 4];
+        assert_eq!(comp.registers, expected.registers);
+        assert_eq!(comp.position(), expected.position);
+        assert_eq!(comp.outputs, vec![3, 2]);
+
+        assert_eq!(comp.rewind_outputs(2), Some(6));
+        assert_eq!(comp.registers, history[1].registers);
+        assert_eq!(comp.position(), 2);
+        assert_eq!(comp.outputs, vec![]);
+        assert_eq!(comp.rewind_outputs(1), None);
+
+        assert_eq!(comp.rewind(10), 1);
+        assert_eq!(comp.inputs, vec![3]);
+        assert_eq!(comp.registers.to_vec(), &program[..]);
+        comp.run().unwrap();
+        assert_eq!(comp.outputs, vec![3, 2, 1]);
+    }
+}
+
+#[test]
+fn it_replays_from_checkpoints_while_cancelled() {
+    let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+    let mut comp = Computer::new(program.clone());
+    comp.set_journal(Journal::new(2).checkpoints(3, 10));
+    comp.input(3);
+    comp.run().unwrap();
+    let cancel = Arc::new(AtomicBool::new(true));
+    comp.set_cancel_flag(cancel.clone());
+
+    // past the two entries, so replayed from the checkpoint at step 3
+    assert_eq!(comp.rewind(6), 6);
+    assert_eq!(comp.journal().unwrap().steps(), 5);
+    assert_eq!(comp.state, State::Operating);
+    assert_eq!(comp.outputs, vec![3, 2]);
+    assert_eq!(comp.run(), Ok(State::Cancelled));
+    cancel.store(false, Ordering::Relaxed);
+    assert_eq!(comp.run(), Ok(State::Halted));
+    assert_eq!(comp.outputs, vec![3, 2, 1]);
+}
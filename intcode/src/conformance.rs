@@ -0,0 +1,562 @@
+//! Differential testing of the interpreters this repo has grown.
+//!
+//! Day 2 and day 5 each kept the interpreter they were first solved with,
+//! and each understands only part of what `Computer` does. A `Dialect`
+//! describes that part. `Dialect::check` runs an interpreter on every case
+//! in a corpus that stays inside its dialect and reports where it
+//! disagrees with the expected outcome.
+//!
+//! The corpus holds the examples published with days 2, 5 and 9, plus
+//! programs generated from a fixed seed whose expected outcome is whatever
+//! `Computer` makes of them.
+
+use crate::computer::{Computer, Event};
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::memory::Memory;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Instructions a reference run may take before the case is thrown out as
+/// running forever.
+const FUEL: u64 = 10_000;
+const DATA_CELLS: usize = 8;
+
+/// The part of Intcode an interpreter understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    pub name: &'static str,
+    pub opcodes: &'static [Opcode],
+    pub immediate: bool,
+    pub relative: bool,
+    /// The range every value in memory, every input and every output must
+    /// stay in.
+    pub min: i64,
+    pub max: i64,
+    /// Whether memory may grow past the program.
+    pub grows: bool,
+}
+
+impl Dialect {
+    /// Day 2: add, multiply and halt on unsigned 32-bit cells.
+    pub const DAY2: Dialect = Dialect {
+        name: "day2",
+        opcodes: &[Opcode::Add, Opcode::Mult, Opcode::Halt],
+        immediate: false,
+        relative: false,
+        min: 0,
+        max: u32::MAX as i64,
+        grows: false,
+    };
+
+    /// Day 5: everything but the relative base, on signed 32-bit cells.
+    pub const DAY5: Dialect = Dialect {
+        name: "day5",
+        opcodes: &[
+            Opcode::Add,
+            Opcode::Mult,
+            Opcode::Input,
+            Opcode::Output,
+            Opcode::JumpIfTrue,
+            Opcode::JumpIfFalse,
+            Opcode::LessThan,
+            Opcode::EqualTo,
+            Opcode::Halt,
+        ],
+        immediate: true,
+        relative: false,
+        min: i32::MIN as i64,
+        max: i32::MAX as i64,
+        grows: false,
+    };
+
+    /// The whole of Intcode as `Computer` runs it.
+    pub const FULL: Dialect = Dialect {
+        name: "intcode",
+        opcodes: &[
+            Opcode::Add,
+            Opcode::Mult,
+            Opcode::Input,
+            Opcode::Output,
+            Opcode::JumpIfTrue,
+            Opcode::JumpIfFalse,
+            Opcode::LessThan,
+            Opcode::EqualTo,
+            Opcode::UpdateBase,
+            Opcode::Halt,
+        ],
+        immediate: true,
+        relative: true,
+        min: i64::MIN,
+        max: i64::MAX,
+        grows: true,
+    };
+
+    fn holds(&self, value: i64) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    fn allows(&self, instruction: &Instruction, memory: &Memory, at: usize) -> bool {
+        let arity = instruction.opcode.arity();
+        if !self.opcodes.contains(&instruction.opcode) {
+            return false;
+        }
+        if !self.grows && at + arity >= memory.len() {
+            return false;
+        }
+        instruction.modes[..arity]
+            .iter()
+            .enumerate()
+            .all(|(i, mode)| match mode {
+                Mode::Immediate => self.immediate,
+                Mode::Relative => self.relative,
+                Mode::Position => {
+                    let address = memory.get(at + 1 + i).unwrap_or(-1);
+                    self.grows || (address >= 0 && (address as usize) < memory.len())
+                }
+            })
+    }
+
+    /// Runs `program` on `Computer`, `None` if it strays outside the
+    /// dialect, faults or doesn't halt with the inputs given.
+    fn reference(&self, program: &[i64], inputs: &[i64]) -> Option<Outcome> {
+        if !program.iter().chain(inputs).all(|&value| self.holds(value)) {
+            return None;
+        }
+        let mut comp = Computer::new(program.to_vec());
+        for &input in inputs {
+            comp.input(input);
+        }
+        comp.set_fuel(FUEL);
+        let mut outputs = vec![];
+        loop {
+            let at = comp.position();
+            let instruction = comp.registers.decode(at).ok()?;
+            if !self.allows(&instruction, &comp.registers, at) {
+                return None;
+            }
+            match comp.step().ok()? {
+                Event::Executed => {}
+                Event::Output(value) => outputs.push(value),
+                Event::Halted => break,
+                _ => return None,
+            }
+            let memory = &comp.registers;
+            let grown = memory.len() > program.len() || memory.sparse_cells().count() > 0;
+            if (grown && !self.grows) || !memory.iter().all(|value| self.holds(value)) {
+                return None;
+            }
+        }
+        Some(Outcome {
+            outputs,
+            memory: Some(comp.registers.to_vec()),
+        })
+    }
+
+    /// Whether an interpreter for this dialect can be held to `case`.
+    pub fn fits(&self, case: &Case) -> bool {
+        self.reference(&case.program, &case.inputs).is_some()
+    }
+
+    /// Runs `run` on every case that fits, catching panics, and reports
+    /// every case where the outcome isn't the expected one.
+    pub fn check<F>(&self, cases: &[Case], mut run: F) -> Report
+    where
+        F: FnMut(&Case) -> Outcome,
+    {
+        let mut report = Report {
+            dialect: self.name,
+            checked: 0,
+            disagreements: vec![],
+        };
+        for case in cases.iter().filter(|case| self.fits(case)) {
+            report.checked += 1;
+            let actual =
+                panic::catch_unwind(AssertUnwindSafe(|| run(case))).map_err(
+                    |payload| match payload.downcast_ref::<&str>() {
+                        Some(message) => message.to_string(),
+                        None => payload
+                            .downcast_ref::<String>()
+                            .cloned()
+                            .unwrap_or_else(|| "panicked".to_string()),
+                    },
+                );
+            let agrees = match &actual {
+                Ok(outcome) => case.expected().agrees(outcome),
+                Err(_) => false,
+            };
+            if !agrees {
+                report.disagreements.push(Disagreement {
+                    case: case.name.clone(),
+                    expected: case.expected(),
+                    actual,
+                });
+            }
+        }
+        report
+    }
+}
+
+/// A program, the inputs it's fed and what it should leave behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub name: String,
+    pub program: Vec<i64>,
+    pub inputs: Vec<i64>,
+    /// Memory once halted, `None` where only the outputs were published.
+    pub memory: Option<Vec<i64>>,
+    pub outputs: Vec<i64>,
+}
+
+impl Case {
+    fn published(name: &str, program: &[i64], inputs: &[i64], outputs: &[i64]) -> Self {
+        Self {
+            name: name.to_string(),
+            program: program.to_vec(),
+            inputs: inputs.to_vec(),
+            memory: None,
+            outputs: outputs.to_vec(),
+        }
+    }
+
+    fn halting_with(mut self, memory: &[i64]) -> Self {
+        self.memory = Some(memory.to_vec());
+        self
+    }
+
+    fn expected(&self) -> Outcome {
+        Outcome {
+            outputs: self.outputs.clone(),
+            memory: self.memory.clone(),
+        }
+    }
+}
+
+/// What an interpreter did with a case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub outputs: Vec<i64>,
+    /// Memory once halted, `None` for interpreters that can't show it.
+    pub memory: Option<Vec<i64>>,
+}
+
+impl Outcome {
+    /// Whether `actual` matches this expected outcome, ignoring memory
+    /// either side doesn't know.
+    fn agrees(&self, actual: &Outcome) -> bool {
+        self.outputs == actual.outputs
+            && match (&self.memory, &actual.memory) {
+                (Some(expected), Some(actual)) => expected == actual,
+                _ => true,
+            }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "outputs {:?}", self.outputs)?;
+        if let Some(memory) = &self.memory {
+            write!(f, ", memory {:?}", memory)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement {
+    pub case: String,
+    pub expected: Outcome,
+    /// What the interpreter did instead, or the message it panicked with.
+    pub actual: Result<Outcome, String>,
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: expected {}, ", self.case, self.expected)?;
+        match &self.actual {
+            Ok(outcome) => write!(f, "got {}", outcome),
+            Err(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub dialect: &'static str,
+    /// How many cases fit the dialect and were run.
+    pub checked: usize,
+    pub disagreements: Vec<Disagreement>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} cases, {} disagreements",
+            self.dialect,
+            self.checked,
+            self.disagreements.len()
+        )?;
+        for disagreement in &self.disagreements {
+            writeln!(f, "  {}", disagreement)?;
+        }
+        Ok(())
+    }
+}
+
+/// The examples from the puzzle texts of days 2, 5 and 9.
+fn published() -> Vec<Case> {
+    let mut cases = vec![
+        Case::published(
+            "day2 example",
+            &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+            &[],
+            &[],
+        )
+        .halting_with(&[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]),
+        Case::published("day2 add", &[1, 0, 0, 0, 99], &[], &[]).halting_with(&[2, 0, 0, 0, 99]),
+        Case::published("day2 multiply", &[2, 3, 0, 3, 99], &[], &[])
+            .halting_with(&[2, 3, 0, 6, 99]),
+        Case::published("day2 past the halt", &[2, 4, 4, 5, 99, 0], &[], &[])
+            .halting_with(&[2, 4, 4, 5, 99, 9801]),
+        Case::published(
+            "day2 self-modifying",
+            &[1, 1, 1, 4, 99, 5, 6, 0, 99],
+            &[],
+            &[],
+        )
+        .halting_with(&[30, 1, 1, 4, 2, 5, 6, 0, 99]),
+        Case::published("day5 echo", &[3, 0, 4, 0, 99], &[42], &[42])
+            .halting_with(&[42, 0, 4, 0, 99]),
+        Case::published("day5 modes", &[1002, 4, 3, 4, 33], &[], &[])
+            .halting_with(&[1002, 4, 3, 4, 99]),
+        Case::published("day5 negatives", &[1101, 100, -1, 4, 0], &[], &[])
+            .halting_with(&[1101, 100, -1, 4, 99]),
+        Case::published("day9 quine", &QUINE, &[], &QUINE),
+        Case::published(
+            "day9 sixteen digits",
+            &[1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0],
+            &[],
+            &[1_219_070_632_396_864],
+        ),
+        Case::published(
+            "day9 large number",
+            &[104, 1_125_899_906_842_624, 99],
+            &[],
+            &[1_125_899_906_842_624],
+        ),
+    ];
+
+    let comparisons: [Comparison; 6] = [
+        (
+            "day5 equal to 8",
+            &[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8],
+            |input| i64::from(input == 8),
+        ),
+        (
+            "day5 less than 8",
+            &[3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8],
+            |input| i64::from(input < 8),
+        ),
+        (
+            "day5 immediate equal to 8",
+            &[3, 3, 1108, -1, 8, 3, 4, 3, 99],
+            |input| i64::from(input == 8),
+        ),
+        (
+            "day5 immediate less than 8",
+            &[3, 3, 1107, -1, 8, 3, 4, 3, 99],
+            |input| i64::from(input < 8),
+        ),
+        (
+            "day5 position jump",
+            &[3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9],
+            |input| i64::from(input != 0),
+        ),
+        (
+            "day5 immediate jump",
+            &[3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1],
+            |input| i64::from(input != 0),
+        ),
+    ];
+    for (name, program, expected) in comparisons.iter() {
+        for &input in &[-3, 0, 7, 8, 9] {
+            cases.push(Case::published(
+                &format!("{} given {}", name, input),
+                program,
+                &[input],
+                &[expected(input)],
+            ));
+        }
+    }
+    for &input in &[7, 8, 9] {
+        cases.push(Case::published(
+            &format!("day5 compared to 8 given {}", input),
+            &AROUND_EIGHT,
+            &[input],
+            &[999 + (input - 7)],
+        ));
+    }
+    cases
+}
+
+/// A published program fed one input, with the output it should give.
+type Comparison = (&'static str, &'static [i64], fn(i64) -> i64);
+
+const QUINE: [i64; 16] = [
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+const AROUND_EIGHT: [i64; 47] = [
+    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+    1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105,
+    1, 46, 98, 99,
+];
+
+/// A xorshift generator, so the generated corpus is the same every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// A small program in `dialect`: a few random instructions, a halt, then
+/// some data. Parameters mostly point at the data but now and then at
+/// the code, so some programs modify themselves.
+fn random_program(dialect: &Dialect, rng: &mut Rng) -> (Vec<i64>, Vec<i64>) {
+    let opcodes = dialect
+        .opcodes
+        .iter()
+        .copied()
+        .filter(|&opcode| opcode != Opcode::Halt && opcode != Opcode::UpdateBase)
+        .collect::<Vec<_>>();
+    let small = |rng: &mut Rng| {
+        let value = rng.below(20) as i64;
+        if dialect.min < 0 {
+            value - 5
+        } else {
+            value
+        }
+    };
+
+    let instructions = (0..2 + rng.below(8))
+        .map(|_| opcodes[rng.below(opcodes.len())])
+        .collect::<Vec<_>>();
+    let mut starts = vec![];
+    let mut end = 0;
+    for opcode in &instructions {
+        starts.push(end as i64);
+        end += 1 + opcode.arity();
+    }
+    // the halt
+    starts.push(end as i64);
+    let data = end + 1;
+    let len = data + DATA_CELLS;
+
+    let mut program = vec![];
+    for opcode in instructions {
+        let mut modes = [Mode::Position; 3];
+        let mut params = vec![];
+        for (i, mode) in modes.iter_mut().enumerate().take(opcode.arity()) {
+            let writes = opcode.writes() == Some(i);
+            let target = i == 1 && (opcode == Opcode::JumpIfTrue || opcode == Opcode::JumpIfFalse);
+            if dialect.immediate && !writes && rng.below(2) == 0 {
+                *mode = Mode::Immediate;
+                params.push(if target {
+                    starts[rng.below(starts.len())]
+                } else {
+                    small(rng)
+                });
+            } else if rng.below(8) == 0 {
+                params.push(rng.below(len) as i64);
+            } else {
+                params.push((data + rng.below(DATA_CELLS)) as i64);
+            }
+        }
+        program.push(Instruction { opcode, modes }.encode());
+        program.extend(params);
+    }
+    program.push(Opcode::Halt.code());
+    program.extend((0..DATA_CELLS).map(|_| small(rng)));
+    let inputs = (0..4).map(|_| small(rng)).collect();
+    (program, inputs)
+}
+
+/// Up to `count` generated programs that run to a halt in `dialect`,
+/// expecting what `Computer` does with them.
+pub fn generate(dialect: &Dialect, seed: u64, count: usize) -> Vec<Case> {
+    let mut rng = Rng(seed.max(1));
+    let mut cases = vec![];
+    for _ in 0..count * 20 {
+        if cases.len() == count {
+            break;
+        }
+        let (program, inputs) = random_program(dialect, &mut rng);
+        if let Some(outcome) = dialect.reference(&program, &inputs) {
+            cases.push(Case {
+                name: format!("{} generated {}", dialect.name, cases.len()),
+                program,
+                inputs,
+                memory: outcome.memory,
+                outputs: outcome.outputs,
+            });
+        }
+    }
+    cases
+}
+
+/// The published examples followed by generated programs for days 2
+/// and 5.
+pub fn corpus() -> Vec<Case> {
+    let mut cases = published();
+    cases.extend(generate(&Dialect::DAY2, 2, 100));
+    cases.extend(generate(&Dialect::DAY5, 5, 200));
+    cases
+}
+
+#[test]
+fn it_agrees_with_the_corpus() {
+    let cases = corpus();
+    let report = Dialect::FULL.check(&cases, |case| {
+        let mut comp = Computer::new(case.program.clone());
+        for &input in &case.inputs {
+            comp.input(input);
+        }
+        comp.run().expect("intcode program faulted");
+        Outcome {
+            outputs: comp.outputs.iter().copied().collect(),
+            memory: Some(comp.registers.to_vec()),
+        }
+    });
+    assert_eq!(report.checked, cases.len(), "{}", report);
+    assert!(report.disagreements.is_empty(), "{}", report);
+    assert!(cases.iter().filter(|case| Dialect::DAY2.fits(case)).count() > 100);
+    assert!(cases.iter().filter(|case| Dialect::DAY5.fits(case)).count() > 200);
+}
+
+#[test]
+fn it_reports_disagreements() {
+    let cases = published();
+    let report = Dialect::DAY2.check(&cases, |case| {
+        if case.name == "day2 add" {
+            panic!("off by one");
+        }
+        Outcome {
+            outputs: vec![],
+            memory: case.memory.clone(),
+        }
+    });
+    assert_eq!(report.checked, 5);
+    assert_eq!(
+        report.to_string(),
+        "day2: 5 cases, 1 disagreements\n  day2 add: expected outputs [], memory [2, 0, 0, 0, 99], panicked: off by one\n"
+    );
+}
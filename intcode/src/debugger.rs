@@ -0,0 +1,482 @@
+//! A line driven debugger around `Computer`, used by the `debug` binary.
+//!
+//! Commands, one per line:
+//!
+//! ```text
+//! break <address|mnemonic>   stop before an address or any instruction of a kind
+//! watch <address>            stop after a cell changes
+//! delete <address|mnemonic>  remove a breakpoint or watchpoint
+//! step [n]                   execute n instructions, 1 by default
+//! continue                   run until a breakpoint, watchpoint, input or halt
+//! back [n]                   undo n instructions, 1 by default
+//! rcontinue                  run backwards to a breakpoint or the last output
+//! regs                       instruction pointer, relative base, state and queues
+//! print <address> [count]    dump up to 1024 cells of memory
+//! poke <address> <value>     write memory
+//! input <value>...           queue integer inputs
+//! ascii <text>               queue text followed by a newline
+//! list                       disassemble the next instruction
+//! help                       list the commands
+//! quit                       leave the debugger
+//! ```
+//!
+//! Every command but `poke` has a short form: `b`, `w`, `d`, `s`, `c`,
+//! `bk`, `rc`, `r`, `p`, `i`, `a`, `l`, `h` and `q`.
+
+use crate::computer::{Computer, Event};
+use crate::disasm::Line;
+use crate::instruction::{Instruction, Opcode};
+use crate::journal::Journal;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "commands: break, watch, delete, step, continue, back, rcontinue, regs, print, \
+                    poke, input, ascii, list, quit";
+
+pub struct Debugger {
+    pub comp: Computer,
+    breakpoints: BTreeSet<usize>,
+    opcode_breaks: Vec<Opcode>,
+    watchpoints: BTreeMap<usize, i64>,
+}
+
+/// Why a `step` or `continue` handed control back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stop {
+    Stepped,
+    Breakpoint(usize),
+    Opcode(Opcode),
+    Watchpoint { address: usize, old: i64, new: i64 },
+    NeedsInput,
+    Halted,
+    Fault(String),
+}
+
+fn opcode(name: &str) -> Option<Opcode> {
+    Opcode::ALL
+        .iter()
+        .copied()
+        .find(|opcode| opcode.mnemonic().eq_ignore_ascii_case(name))
+}
+
+fn render_outputs(outputs: &[i64]) -> String {
+    if outputs
+        .iter()
+        .all(|&value| value == 10 || (32..127).contains(&value))
+    {
+        outputs.iter().map(|&value| value as u8 as char).collect()
+    } else {
+        let values = outputs.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        format!("{}\n", values.join(", "))
+    }
+}
+
+/// How far back the debugger can run a machine that doesn't have a
+/// journal of its own.
+const JOURNAL_ENTRIES: usize = 100_000;
+const CHECKPOINT_EVERY: u64 = 10_000;
+const CHECKPOINTS: usize = 100;
+
+/// The most cells one `print` dumps, however many are asked for.
+const PRINT_LIMIT: usize = 1024;
+
+impl Debugger {
+    /// Gives `comp` a journal for `back` and `rcontinue` if it has none.
+    pub fn new(mut comp: Computer) -> Self {
+        if comp.journal().is_none() {
+            comp.set_journal(
+                Journal::new(JOURNAL_ENTRIES).checkpoints(CHECKPOINT_EVERY, CHECKPOINTS),
+            );
+        }
+        Self {
+            comp,
+            breakpoints: BTreeSet::new(),
+            opcode_breaks: vec![],
+            watchpoints: BTreeMap::new(),
+        }
+    }
+
+    /// The instruction at the instruction pointer as a listing line.
+    fn current(&self) -> String {
+        let position = self.comp.position();
+        let word = self.comp.registers.get(position).unwrap_or(0);
+        match Instruction::decode(word) {
+            Ok(instruction) => {
+                let params = (1..=instruction.opcode.arity())
+                    .map(|i| self.comp.registers.get(position + i).unwrap_or(0))
+                    .collect();
+                Line::Code {
+                    address: position,
+                    instruction,
+                    params,
+                }
+                .to_string()
+            }
+            Err(_) => Line::Data {
+                address: position,
+                values: vec![word],
+            }
+            .to_string(),
+        }
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.comp.registers.get(address).unwrap_or(0)
+    }
+
+    /// Executes one instruction, collecting any output into `outputs`.
+    fn single_step(&mut self, outputs: &mut Vec<i64>) -> Stop {
+        match self.comp.step() {
+            Ok(Event::Output(value)) => outputs.push(value),
+            Ok(Event::Executed) => {}
+            Ok(Event::NeedsInput) => return Stop::NeedsInput,
+            Ok(Event::Halted) => return Stop::Halted,
+            Ok(event) => return Stop::Fault(format!("{:?}", event)),
+            Err(error) => return Stop::Fault(error.to_string()),
+        }
+        let changed = self
+            .watchpoints
+            .iter()
+            .map(|(&address, &old)| (address, old, self.read(address)))
+            .find(|&(_, old, new)| old != new);
+        if let Some((address, old, new)) = changed {
+            self.watchpoints.insert(address, new);
+            return Stop::Watchpoint { address, old, new };
+        }
+        Stop::Stepped
+    }
+
+    fn breakpoint(&self) -> Option<Stop> {
+        let position = self.comp.position();
+        if self.breakpoints.contains(&position) {
+            return Some(Stop::Breakpoint(position));
+        }
+        let word = self.comp.registers.get(position).ok()?;
+        let instruction = Instruction::decode(word).ok()?;
+        if self.opcode_breaks.contains(&instruction.opcode) {
+            return Some(Stop::Opcode(instruction.opcode));
+        }
+        None
+    }
+
+    fn resume(&mut self, count: Option<usize>) -> String {
+        let mut outputs = vec![];
+        let mut stop = Stop::Stepped;
+        let mut executed = 0;
+        while count.is_none_or(|count| executed < count) {
+            if executed > 0 {
+                if let Some(breakpoint) = self.breakpoint() {
+                    stop = breakpoint;
+                    break;
+                }
+            }
+            stop = self.single_step(&mut outputs);
+            if stop != Stop::Stepped {
+                break;
+            }
+            executed += 1;
+        }
+
+        let mut report = String::new();
+        if !outputs.is_empty() {
+            report += &render_outputs(&outputs);
+            if !report.ends_with('\n') {
+                report.push('\n');
+            }
+        }
+        report += &match stop {
+            Stop::Stepped => String::new(),
+            Stop::Breakpoint(address) => format!("breakpoint at {:04}\n", address),
+            Stop::Opcode(opcode) => format!("breakpoint on {}\n", opcode.mnemonic()),
+            Stop::Watchpoint { address, old, new } => {
+                format!("watchpoint {:04}: {} -> {}\n", address, old, new)
+            }
+            Stop::NeedsInput => "waiting for input\n".to_string(),
+            Stop::Halted => "halted\n".to_string(),
+            Stop::Fault(message) => format!("fault: {}\n", message),
+        };
+        report + &self.current() + "\n"
+    }
+
+    /// Runs backwards `count` instructions, or with `None` until a
+    /// breakpoint or the instruction that produced the last output.
+    fn reverse(&mut self, count: Option<u64>) -> String {
+        let mut undone = 0;
+        let mut stop = None;
+        while count.is_none_or(|count| undone < count) {
+            let journal = self.comp.journal().unwrap();
+            let steps = journal.steps();
+            let output = steps > 0 && journal.output_step(1) == Some(steps - 1);
+            if self.comp.rewind(1) == 0 {
+                stop = Some("start of the journal\n".to_string());
+                break;
+            }
+            undone += 1;
+            if count.is_none() {
+                if output {
+                    stop = Some("before output\n".to_string());
+                    break;
+                }
+                if let Some(Stop::Breakpoint(address)) = self.breakpoint() {
+                    stop = Some(format!("breakpoint at {:04}\n", address));
+                    break;
+                }
+            }
+        }
+        for (&address, value) in self.watchpoints.iter_mut() {
+            *value = self.comp.registers.get(address).unwrap_or(0);
+        }
+        format!("rewound {}\n", undone) + &stop.unwrap_or_default() + &self.current() + "\n"
+    }
+
+    fn regs(&self) -> String {
+        let inputs = self
+            .comp
+            .inputs
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        format!(
+            "ip {:04}  rb {}  state {:?}\ninputs [{}]\n",
+            self.comp.position(),
+            self.comp.relative_base(),
+            self.comp.state,
+            inputs.join(", ")
+        )
+    }
+
+    fn print(&self, address: usize, count: usize) -> Result<String, String> {
+        let max_address = self.comp.registers.limits().max_address;
+        let end = address
+            .checked_add(count.min(PRINT_LIMIT))
+            .filter(|&end| end <= max_address.saturating_add(1))
+            .ok_or_else(|| {
+                format!(
+                    "{} cells from {} run past address {}",
+                    count, address, max_address
+                )
+            })?;
+        Ok((address..end)
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(|row| {
+                let values = row.iter().map(|&a| self.read(a).to_string());
+                format!("{:04}: {}\n", row[0], values.collect::<Vec<_>>().join(", "))
+            })
+            .collect())
+    }
+
+    /// Runs one command line and returns what it prints.
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (name, rest) = match line.find(char::is_whitespace) {
+            Some(split) => (&line[..split], line[split..].trim()),
+            None => (line, ""),
+        };
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+        let number = |index: usize| -> Result<i64, String> {
+            let arg = args
+                .get(index)
+                .ok_or_else(|| format!("{} needs more arguments", name))?;
+            arg.parse::<i64>()
+                .map_err(|_| format!("`{}` is not a number", arg))
+        };
+        let address = |index: usize| -> Result<usize, String> {
+            let value = number(index)?;
+            if value < 0 {
+                return Err(format!("{} is not an address", value));
+            }
+            Ok(value as usize)
+        };
+        let count = |index: usize| -> Result<usize, String> {
+            let value = number(index)?;
+            if value < 1 {
+                return Err(format!("{} is not a count, counts start at 1", value));
+            }
+            Ok(value as usize)
+        };
+
+        match name {
+            "" => Ok(String::new()),
+            "break" | "b" => match args.first().and_then(|arg| opcode(arg)) {
+                Some(opcode) => {
+                    if !self.opcode_breaks.contains(&opcode) {
+                        self.opcode_breaks.push(opcode);
+                    }
+                    Ok(format!("breakpoint on {}\n", opcode.mnemonic()))
+                }
+                None => {
+                    let address = address(0)?;
+                    self.breakpoints.insert(address);
+                    Ok(format!("breakpoint at {:04}\n", address))
+                }
+            },
+            "watch" | "w" => {
+                let address = address(0)?;
+                self.watchpoints.insert(address, self.read(address));
+                Ok(format!("watching {:04}\n", address))
+            }
+            "delete" | "d" => match args.first().and_then(|arg| opcode(arg)) {
+                Some(opcode) => {
+                    self.opcode_breaks.retain(|&o| o != opcode);
+                    Ok(String::new())
+                }
+                None => {
+                    let address = address(0)?;
+                    self.breakpoints.remove(&address);
+                    self.watchpoints.remove(&address);
+                    Ok(String::new())
+                }
+            },
+            "step" | "s" => {
+                let count = if args.is_empty() { 1 } else { count(0)? };
+                Ok(self.resume(Some(count)))
+            }
+            "continue" | "c" => Ok(self.resume(None)),
+            "back" | "bk" => {
+                let count = if args.is_empty() { 1 } else { count(0)? };
+                Ok(self.reverse(Some(count as u64)))
+            }
+            "rcontinue" | "rc" => Ok(self.reverse(None)),
+            "regs" | "r" => Ok(self.regs()),
+            "print" | "p" => {
+                let count = if args.len() > 1 { count(1)? } else { 1 };
+                self.print(address(0)?, count)
+            }
+            "poke" => {
+                let address = address(0)?;
+                let value = number(1)?;
+                self.comp
+                    .registers
+                    .set(address, value)
+                    .map_err(|kind| kind.to_string())?;
+                if let Some(watched) = self.watchpoints.get_mut(&address) {
+                    *watched = value;
+                }
+                Ok(String::new())
+            }
+            "input" | "i" => {
+                for index in 0..args.len() {
+                    let value = number(index)?;
+                    self.comp.input(value);
+                }
+                Ok(String::new())
+            }
+            "ascii" | "a" => {
+                for byte in rest.bytes().chain(Some(b'\n')) {
+                    self.comp.input(byte as i64);
+                }
+                Ok(String::new())
+            }
+            "list" | "l" => Ok(self.current() + "\n"),
+            "help" | "h" => Ok(format!("{}\n", HELP)),
+            _ => Err(format!("unknown command `{}`, try help", name)),
+        }
+    }
+
+    /// Reads commands from `input` until it ends or `quit` is entered.
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        write!(output, "{}\n(debug) ", self.current())?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            if matches!(line.trim(), "quit" | "q") {
+                break;
+            }
+            match self.command(&line) {
+                Ok(text) => write!(output, "{}", text)?,
+                Err(message) => writeln!(output, "{}", message)?,
+            }
+            write!(output, "(debug) ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn it_stops_on_breakpoints_and_watchpoints() {
+    // counts 3 down to 1 through cell 12
+    let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+    let mut debugger = Debugger::new(Computer::new(program));
+    debugger.command("input 3").unwrap();
+    debugger.command("break 8").unwrap();
+
+    let report = debugger.command("continue").unwrap();
+    assert_eq!(report, "3\nbreakpoint at 0008\n0008: JT [12], #2\n");
+
+    debugger.command("delete 8").unwrap();
+    debugger.command("watch 12").unwrap();
+    let report = debugger.command("c").unwrap();
+    assert!(report.contains("watchpoint 0012: 2 -> 1\n"));
+    assert_eq!(debugger.comp.position(), 8);
+
+    debugger.command("break out").unwrap();
+    debugger.command("delete 12").unwrap();
+    assert!(debugger.command("c").unwrap().contains("breakpoint on OUT"));
+    assert_eq!(debugger.comp.position(), 2);
+}
+
+#[test]
+fn it_steps_pokes_and_prints() {
+    let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+    let mut debugger = Debugger::new(Computer::new(program));
+    assert!(debugger
+        .command("step")
+        .unwrap()
+        .starts_with("waiting for input"));
+    debugger.command("ascii A").unwrap();
+    assert_eq!(debugger.comp.inputs, vec![65, 10]);
+
+    debugger.command("step 2").unwrap();
+    assert_eq!(debugger.command("print 12").unwrap(), "0012: 65\n");
+    debugger.command("poke 12 1").unwrap();
+    assert_eq!(debugger.command("p 11 2").unwrap(), "0011: 99, 1\n");
+    assert_eq!(
+        debugger.command("regs").unwrap(),
+        "ip 0004  rb 0  state Operating\ninputs [10]\n"
+    );
+    assert!(debugger.command("c").unwrap().starts_with("halted"));
+    assert_eq!(
+        debugger.command("print 0 100000").unwrap().lines().count(),
+        128
+    );
+    assert!(debugger.command("print 9223372036854775807 2").is_err());
+    assert!(debugger.command("poke x 1").is_err());
+    for command in &["step 0", "back -1", "print 12 0"] {
+        assert!(debugger
+            .command(command)
+            .unwrap_err()
+            .contains("not a count"));
+    }
+    assert!(debugger.command("frobnicate").is_err());
+}
+
+#[test]
+fn it_runs_backwards() {
+    let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+    let mut debugger = Debugger::new(Computer::new(program));
+    debugger.command("input 3").unwrap();
+    assert!(debugger
+        .command("c")
+        .unwrap()
+        .starts_with("3, 2, 1\nhalted"));
+
+    let report = debugger.command("rc").unwrap();
+    assert_eq!(report, "rewound 4\nbefore output\n0002: OUT [12]\n");
+    assert_eq!(debugger.command("print 12").unwrap(), "0012: 1\n");
+    let report = debugger.command("back 2").unwrap();
+    assert_eq!(report, "rewound 2\n0004: ADD [12], #-1 -> [12]\n");
+    assert_eq!(debugger.command("print 12").unwrap(), "0012: 2\n");
+
+    debugger.command("break 0").unwrap();
+    let report = debugger.command("rc").unwrap();
+    assert!(report.starts_with("rewound 1\nbefore output\n"));
+    let report = debugger.command("rc").unwrap();
+    assert!(report.starts_with("rewound 3\nbefore output\n"));
+    let report = debugger.command("rc").unwrap();
+    assert!(report.starts_with("rewound 1\nbreakpoint at 0000\n"));
+    let report = debugger.command("rc").unwrap();
+    assert!(report.starts_with("rewound 0\nstart of the journal\n"));
+    assert_eq!(debugger.comp.inputs, vec![3]);
+}
@@ -0,0 +1,198 @@
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::fmt;
+
+/// One line of a listing, either a decoded instruction or a run of data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Code {
+        address: usize,
+        instruction: Instruction,
+        params: Vec<i64>,
+    },
+    Data {
+        address: usize,
+        values: Vec<i64>,
+    },
+}
+
+const DATA_PER_LINE: usize = 8;
+
+fn operand(mode: Mode, value: i64) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", value),
+        Mode::Immediate => format!("#{}", value),
+        Mode::Relative if value < 0 => format!("[rb{}]", value),
+        Mode::Relative => format!("[rb+{}]", value),
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Code {
+                address,
+                instruction,
+                params,
+            } => {
+                let opcode = instruction.opcode;
+                write!(f, "{:04}: {}", address, opcode.mnemonic())?;
+                let mut separator = " ";
+                for (i, (&mode, &value)) in instruction.modes.iter().zip(params).enumerate() {
+                    if opcode.writes() == Some(i) {
+                        separator = " -> ";
+                    }
+                    write!(f, "{}{}", separator, operand(mode, value))?;
+                    separator = ", ";
+                }
+                Ok(())
+            }
+            Line::Data { address, values } => {
+                let values = values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                write!(f, "{:04}: DATA {}", address, values.join(", "))
+            }
+        }
+    }
+}
+
+fn decode_at(program: &[i64], address: usize) -> Option<Instruction> {
+    let instruction = Instruction::decode(*program.get(address)?).ok()?;
+    let opcode = instruction.opcode;
+    if address + opcode.arity() >= program.len() {
+        return None;
+    }
+    if let Some(write) = opcode.writes() {
+        if instruction.modes[write] == Mode::Immediate {
+            return None;
+        }
+    }
+    Some(instruction)
+}
+
+/// Marks the addresses that start an instruction.
+///
+/// Control flow is followed from address 0 through fall-through and
+/// immediate jump targets. Constants built from two immediates are tried as
+/// extra entry points afterwards, since that is how programs push return
+/// addresses before an indirect jump.
+pub fn find_code(program: &[i64]) -> Vec<bool> {
+    let mut starts = vec![false; program.len()];
+    let mut covered = vec![false; program.len()];
+    let mut constants = vec![];
+    let mut pending = vec![0];
+
+    loop {
+        while let Some(mut address) = pending.pop() {
+            while address < program.len() && !covered[address] {
+                let instruction = match decode_at(program, address) {
+                    Some(instruction) => instruction,
+                    None => break,
+                };
+                let opcode = instruction.opcode;
+                let params = &program[address + 1..=address + opcode.arity()];
+                starts[address] = true;
+                for cell in &mut covered[address..=address + opcode.arity()] {
+                    *cell = true;
+                }
+
+                match opcode {
+                    Opcode::Halt => break,
+                    Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                        if instruction.modes[1] == Mode::Immediate && params[1] >= 0 {
+                            pending.push(params[1] as usize);
+                        }
+                        let always = (params[0] != 0) == (opcode == Opcode::JumpIfTrue);
+                        if instruction.modes[0] == Mode::Immediate && always {
+                            break;
+                        }
+                    }
+                    Opcode::Add | Opcode::Mult
+                        if instruction.modes[..2] == [Mode::Immediate; 2] =>
+                    {
+                        let value = if opcode == Opcode::Add {
+                            params[0].wrapping_add(params[1])
+                        } else {
+                            params[0].wrapping_mul(params[1])
+                        };
+                        if value >= 0 {
+                            constants.push(value as usize);
+                        }
+                    }
+                    _ => {}
+                }
+                address += 1 + opcode.arity();
+            }
+        }
+
+        pending.extend(
+            constants
+                .drain(..)
+                .filter(|&address| address < program.len() && !covered[address]),
+        );
+        if pending.is_empty() {
+            return starts;
+        }
+    }
+}
+
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let starts = find_code(program);
+    let mut lines = vec![];
+    let mut address = 0;
+
+    while address < program.len() {
+        if starts[address] {
+            let instruction = decode_at(program, address).unwrap();
+            let arity = instruction.opcode.arity();
+            lines.push(Line::Code {
+                address,
+                instruction,
+                params: program[address + 1..=address + arity].to_vec(),
+            });
+            address += 1 + arity;
+        } else {
+            let end = (address + 1..program.len())
+                .find(|&end| starts[end] || end - address == DATA_PER_LINE)
+                .unwrap_or(program.len());
+            lines.push(Line::Data {
+                address,
+                values: program[address..end].to_vec(),
+            });
+            address = end;
+        }
+    }
+    lines
+}
+
+/// Renders `program` as an address annotated listing, one line per
+/// instruction or run of data.
+pub fn listing(program: &[i64]) -> String {
+    disassemble(program)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[test]
+fn it_renders_operands_by_mode() {
+    assert_eq!(
+        listing(&[1201, 3, 5, 42, 204, -1, 3, 7, 99]),
+        "0000: ADD [rb+3], #5 -> [42]\n0004: OUT [rb-1]\n0006: IN -> [7]\n0008: HLT\n"
+    );
+}
+
+#[test]
+fn it_separates_code_from_data() {
+    assert_eq!(
+        listing(&[1105, 1, 5, 42, 43, 104, 1, 99, 1, 2, 3]),
+        "0000: JT #1, #5\n0003: DATA 42, 43\n0005: OUT #1\n0007: HLT\n0008: DATA 1, 2, 3\n"
+    );
+}
+
+#[test]
+fn it_follows_pushed_return_addresses() {
+    // pushes 9 as a return address then jumps through it
+    let program = [21101, 9, 0, 0, 2105, 1, 0, 7, 7, 104, 0, 99];
+    let starts = find_code(&program);
+    assert!(starts[9]);
+    assert!(!starts[7]);
+}
@@ -0,0 +1,59 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnknownOpcode(i64),
+    UnknownMode(u32),
+    NegativeAddress(i64),
+    ImmediateWrite,
+    OutOfBounds(usize),
+    MemoryExhausted(usize),
+    /// An `Add` or `Mult` result out of range under `Arithmetic::Checked`,
+    /// or a relative base or relative address out of range under any.
+    Overflow,
+    /// A cell holding more than 64 bits read where an `i64` is needed.
+    BigValue(usize),
+    /// Raised by the handler of a user opcode.
+    Extension(String),
+}
+
+/// A fault raised while executing the instruction at `position`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmError {
+    pub position: usize,
+    pub instruction: i64,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode),
+            ErrorKind::UnknownMode(mode) => write!(f, "unknown parameter mode {}", mode),
+            ErrorKind::NegativeAddress(address) => write!(f, "negative address {}", address),
+            ErrorKind::ImmediateWrite => write!(f, "write through immediate mode parameter"),
+            ErrorKind::OutOfBounds(address) => write!(f, "address {} is out of bounds", address),
+            ErrorKind::MemoryExhausted(address) => {
+                write!(f, "no memory left to write address {}", address)
+            }
+            ErrorKind::Overflow => write!(f, "arithmetic overflow"),
+            ErrorKind::BigValue(address) => {
+                write!(f, "value at {} doesn't fit in 64 bits", address)
+            }
+            ErrorKind::Extension(message) => write!(f, "user opcode failed: {}", message),
+        }
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {:04} (instruction {})",
+            self.kind, self.position, self.instruction
+        )
+    }
+}
+
+impl error::Error for VmError {}
@@ -0,0 +1,255 @@
+//! Opcodes registered by the host on top of the built-in instruction set,
+//! see `Computer::set_instruction_set`.
+//!
+//! A user opcode has a two digit code the built-in set doesn't use, a
+//! direction for each parameter and a handler. Parameters are decoded
+//! with the usual modes before the handler runs: it gets the value of
+//! every read parameter and the address of every write parameter, plus
+//! access to memory, inputs and the relative base through a `Call`.
+//!
+//! ```text
+//! let mut set = InstructionSet::new();
+//! set.register(42, "RND", &[Direction::Write], |call| {
+//!     call.set(0, 4)?;
+//!     Ok(Effect::Next)
+//! });
+//! comp.set_instruction_set(set);
+//! ```
+//!
+//! Journals undo user opcodes like any other instruction. Tracers and
+//! profilers only know the built-in opcodes and skip them.
+
+use crate::error::ErrorKind;
+use crate::instruction::Opcode;
+use crate::io::InputSource;
+use crate::memory::Memory;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// Whether a parameter is read from or written through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Read,
+    Write,
+}
+
+/// Where a user opcode leaves the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Carries on with the next instruction.
+    Next,
+    Jump(usize),
+    /// Carries on with the next instruction after outputting a value.
+    Output(i64),
+    /// Suspends the machine on this instruction until there's more input.
+    /// Anything the handler wrote is undone but inputs it took are gone,
+    /// so a handler waiting for input takes a single one before anything
+    /// else.
+    Wait,
+    Halt,
+}
+
+type Handler = Box<dyn Fn(&mut Call) -> Result<Effect, ErrorKind> + Send + Sync>;
+
+pub(crate) struct UserOpcode {
+    pub mnemonic: String,
+    pub params: Vec<Direction>,
+    pub handler: Handler,
+}
+
+/// The user opcodes a machine understands besides the built-in ones.
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    opcodes: BTreeMap<u8, Arc<UserOpcode>>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers opcode `code`, replacing any user opcode registered with
+    /// it before.
+    ///
+    /// Panics if `code` is a built-in opcode or doesn't fit in two digits.
+    pub fn register<F>(&mut self, code: u8, mnemonic: &str, params: &[Direction], handler: F)
+    where
+        F: Fn(&mut Call) -> Result<Effect, ErrorKind> + Send + Sync + 'static,
+    {
+        assert!(code < 100, "opcode {} doesn't fit in two digits", code);
+        assert!(
+            Opcode::try_from(code).is_err(),
+            "opcode {} is built in",
+            code
+        );
+        let opcode = UserOpcode {
+            mnemonic: mnemonic.to_string(),
+            params: params.to_vec(),
+            handler: Box::new(handler),
+        };
+        self.opcodes.insert(code, Arc::new(opcode));
+    }
+
+    pub fn mnemonic(&self, code: u8) -> Option<&str> {
+        Some(&self.opcodes.get(&code)?.mnemonic)
+    }
+
+    /// Number of parameters following the instruction word.
+    pub fn arity(&self, code: u8) -> Option<usize> {
+        Some(self.opcodes.get(&code)?.params.len())
+    }
+
+    pub(crate) fn get(&self, word: i64) -> Option<&Arc<UserOpcode>> {
+        if word < 0 {
+            return None;
+        }
+        self.opcodes.get(&((word % 100) as u8))
+    }
+}
+
+/// The machine as a user opcode's handler sees it.
+pub struct Call<'a> {
+    position: usize,
+    params: Vec<i64>,
+    relative_base: i64,
+    registers: &'a mut Memory,
+    inputs: &'a mut dyn InputSource,
+    /// Cells written so far with the values they held before, in order.
+    pub(crate) writes: Vec<(usize, i64)>,
+    pub(crate) taken: Vec<i64>,
+}
+
+impl<'a> Call<'a> {
+    pub(crate) fn new(
+        position: usize,
+        params: Vec<i64>,
+        relative_base: i64,
+        registers: &'a mut Memory,
+        inputs: &'a mut dyn InputSource,
+    ) -> Self {
+        Self {
+            position,
+            params,
+            relative_base,
+            registers,
+            inputs,
+            writes: vec![],
+            taken: vec![],
+        }
+    }
+
+    /// Address of the instruction being executed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Parameter `i`: the value read for a read parameter, the address to
+    /// write for a write parameter.
+    pub fn param(&self, i: usize) -> i64 {
+        self.params[i]
+    }
+
+    pub fn read(&self, address: usize) -> Result<i64, ErrorKind> {
+        self.registers.get(address)
+    }
+
+    pub fn write(&mut self, address: usize, value: i64) -> Result<(), ErrorKind> {
+        let old = self.registers.get(address)?;
+        self.registers.set(address, value)?;
+        self.writes.push((address, old));
+        Ok(())
+    }
+
+    /// Writes through write parameter `i`.
+    pub fn set(&mut self, i: usize, value: i64) -> Result<(), ErrorKind> {
+        self.write(self.params[i] as usize, value)
+    }
+
+    /// Takes the next input, see `Effect::Wait` for when there is none.
+    pub fn input(&mut self) -> Option<i64> {
+        let input = self.inputs.next_input()?;
+        self.taken.push(input);
+        Some(input)
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    /// Puts back everything written, newest first.
+    pub(crate) fn undo(&mut self) {
+        for &(address, old) in self.writes.iter().rev() {
+            self.registers.set(address, old).ok();
+        }
+        self.writes.clear();
+    }
+}
+
+#[test]
+fn it_runs_user_opcodes() {
+    use crate::computer::{Computer, State};
+    use crate::journal::Journal;
+
+    let mut set = InstructionSet::new();
+    // copies `len` cells from `from` to `to`
+    set.register(
+        20,
+        "MCP",
+        &[Direction::Read, Direction::Read, Direction::Read],
+        |call| {
+            let (from, to, len) = (call.param(0), call.param(1), call.param(2));
+            for i in 0..len {
+                let value = call.read((from + i) as usize)?;
+                call.write((to + i) as usize, value)?;
+            }
+            Ok(Effect::Next)
+        },
+    );
+    // adds an input to its first parameter, stores the input through the
+    // second and outputs the sum
+    set.register(21, "ADI", &[Direction::Read, Direction::Write], |call| {
+        let input = match call.input() {
+            Some(input) => input,
+            None => return Ok(Effect::Wait),
+        };
+        call.set(1, input)?;
+        Ok(Effect::Output(call.param(0) + input))
+    });
+    assert_eq!(set.mnemonic(20), Some("MCP"));
+    assert_eq!(set.arity(21), Some(2));
+
+    // MCP #10, #13, #3; ADI [13], [16]; HLT; data
+    let program = vec![11120, 10, 13, 3, 21, 13, 16, 99, 0, 0, 7, 8, 9, 0, 0, 0, 0];
+    let mut comp = Computer::new(program.clone());
+    comp.set_instruction_set(set);
+    comp.set_journal(Journal::new(10));
+    assert_eq!(comp.run(), Ok(State::Waiting));
+    assert_eq!(&comp.registers.to_vec()[13..], &[7, 8, 9, 0]);
+    assert_eq!(comp.position(), 4);
+
+    comp.input(35);
+    assert_eq!(comp.run(), Ok(State::Halted));
+    assert_eq!(comp.outputs, vec![42]);
+    assert_eq!(comp.registers.to_vec()[16], 35);
+
+    assert_eq!(comp.rewind(3), 3);
+    assert_eq!(comp.registers.to_vec(), &program[..]);
+    assert!(comp.outputs.is_empty());
+    assert_eq!(comp.inputs, vec![35]);
+}
+
+#[test]
+fn it_faults_on_opcodes_nobody_registered() {
+    use crate::computer::Computer;
+
+    let mut comp = Computer::new(vec![42, 99]);
+    comp.set_instruction_set(InstructionSet::new());
+    let error = comp.run().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownOpcode(42));
+    assert_eq!(error.position, 0);
+}
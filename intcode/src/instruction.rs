@@ -0,0 +1,199 @@
+use crate::error::ErrorKind;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Add,
+    Mult,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    EqualTo,
+    UpdateBase,
+    Halt,
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = u8;
+
+    fn try_from(i: u8) -> Result<Self, Self::Error> {
+        match i {
+            1 => Ok(Opcode::Add),
+            2 => Ok(Opcode::Mult),
+            3 => Ok(Opcode::Input),
+            4 => Ok(Opcode::Output),
+            5 => Ok(Opcode::JumpIfTrue),
+            6 => Ok(Opcode::JumpIfFalse),
+            7 => Ok(Opcode::LessThan),
+            8 => Ok(Opcode::EqualTo),
+            9 => Ok(Opcode::UpdateBase),
+            99 => Ok(Opcode::Halt),
+            _ => Err(i),
+        }
+    }
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [
+        Opcode::Add,
+        Opcode::Mult,
+        Opcode::Input,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::EqualTo,
+        Opcode::UpdateBase,
+        Opcode::Halt,
+    ];
+
+    /// The two digit code this opcode is encoded as.
+    pub fn code(self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mult => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::EqualTo => 8,
+            Opcode::UpdateBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    /// Number of parameters following the instruction word.
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mult | Opcode::LessThan | Opcode::EqualTo => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::UpdateBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Index of the parameter the instruction writes through, if any.
+    pub fn writes(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Mult | Opcode::LessThan | Opcode::EqualTo => Some(2),
+            Opcode::Input => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Mult => "MUL",
+            Opcode::Input => "IN",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JT",
+            Opcode::JumpIfFalse => "JF",
+            Opcode::LessThan => "LT",
+            Opcode::EqualTo => "EQ",
+            Opcode::UpdateBase => "ARB",
+            Opcode::Halt => "HLT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl TryFrom<u32> for Mode {
+    type Error = u32;
+
+    fn try_from(i: u32) -> Result<Mode, Self::Error> {
+        match i {
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Immediate),
+            2 => Ok(Mode::Relative),
+            _ => Err(i),
+        }
+    }
+}
+
+impl Mode {
+    /// The digit this mode is encoded as.
+    pub fn code(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+/// An instruction word split into its opcode and parameter modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    pub fn decode(word: i64) -> Result<Self, ErrorKind> {
+        if word < 0 {
+            return Err(ErrorKind::UnknownOpcode(word));
+        }
+        let opcode = (word % 100) as u8;
+        let opcode =
+            Opcode::try_from(opcode).map_err(|opcode| ErrorKind::UnknownOpcode(opcode as i64))?;
+
+        let mut modes = [Mode::Position; 3];
+        let mut digits = word / 100;
+        for mode in modes.iter_mut().take(opcode.arity()) {
+            *mode = Mode::try_from((digits % 10) as u32).map_err(ErrorKind::UnknownMode)?;
+            digits /= 10;
+        }
+        Ok(Self { opcode, modes })
+    }
+
+    pub fn encode(&self) -> i64 {
+        self.modes
+            .iter()
+            .rev()
+            .fold(0, |word, mode| word * 10 + mode.code())
+            * 100
+            + self.opcode.code()
+    }
+}
+
+#[test]
+fn it_decodes_opcode_and_modes() {
+    assert_eq!(
+        Instruction::decode(21002),
+        Ok(Instruction {
+            opcode: Opcode::Mult,
+            modes: [Mode::Position, Mode::Immediate, Mode::Relative],
+        })
+    );
+    assert_eq!(
+        Instruction::decode(99),
+        Ok(Instruction {
+            opcode: Opcode::Halt,
+            modes: [Mode::Position; 3],
+        })
+    );
+}
+
+#[test]
+fn it_rejects_bad_words() {
+    assert_eq!(Instruction::decode(42), Err(ErrorKind::UnknownOpcode(42)));
+    assert_eq!(Instruction::decode(-1), Err(ErrorKind::UnknownOpcode(-1)));
+    assert_eq!(Instruction::decode(301), Err(ErrorKind::UnknownMode(3)));
+}
+
+#[test]
+fn it_encodes_what_it_decodes() {
+    for &word in &[1, 99, 1002, 21101, 204, 1105, 22201] {
+        assert_eq!(Instruction::decode(word).unwrap().encode(), word);
+    }
+}
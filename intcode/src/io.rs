@@ -0,0 +1,203 @@
+//! Where a `Computer` reads its inputs from and writes its outputs to.
+//!
+//! The default machine uses a `VecDeque` for both. `Computer::with_io`
+//! swaps them for channels, stdin/stdout or closures.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+pub trait InputSource {
+    /// The next input, `None` suspends the machine with `State::Waiting`
+    /// until one is available.
+    fn next_input(&mut self) -> Option<i64>;
+}
+
+pub trait OutputSink {
+    fn send_output(&mut self, value: i64);
+}
+
+impl InputSource for VecDeque<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn send_output(&mut self, value: i64) {
+        self.push_back(value);
+    }
+}
+
+/// Blocks until a value arrives, waiting once every sender has gone.
+impl InputSource for Receiver<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+}
+
+/// Outputs sent after the receiver has gone are dropped.
+impl OutputSink for Sender<i64> {
+    fn send_output(&mut self, value: i64) {
+        self.send(value).ok();
+    }
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for F {
+    fn next_input(&mut self) -> Option<i64> {
+        self()
+    }
+}
+
+impl<F: FnMut(i64)> OutputSink for F {
+    fn send_output(&mut self, value: i64) {
+        self(value)
+    }
+}
+
+/// Reads stdin a line at a time, either as ASCII text ending in a newline
+/// or as one number per line.
+pub struct StdinSource<R = io::StdinLock<'static>> {
+    reader: R,
+    ascii: bool,
+    prompt: Option<String>,
+    pending: VecDeque<i64>,
+}
+
+impl StdinSource {
+    pub fn ascii() -> Self {
+        Self::from_reader(io::stdin().lock(), true)
+    }
+
+    pub fn numbers() -> Self {
+        Self::from_reader(io::stdin().lock(), false)
+    }
+}
+
+impl<R: BufRead> StdinSource<R> {
+    pub fn from_reader(reader: R, ascii: bool) -> Self {
+        Self {
+            reader,
+            ascii,
+            prompt: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Printed before each line is read.
+    pub fn prompt(mut self, prompt: &str) -> Self {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        if let Some(prompt) = &self.prompt {
+            println!("{}", prompt);
+        }
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+        }
+    }
+}
+
+impl<R: BufRead> InputSource for StdinSource<R> {
+    fn next_input(&mut self) -> Option<i64> {
+        while self.pending.is_empty() {
+            let line = self.read_line()?;
+            if self.ascii {
+                self.pending.extend(line.bytes().map(i64::from));
+                self.pending.push_back(b'\n' as i64);
+            } else if let Ok(number) = line.trim().parse() {
+                self.pending.push_back(number);
+            } else if !line.trim().is_empty() {
+                eprintln!("`{}` is not a number", line.trim());
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Writes outputs to stdout, either as ASCII text flushed a line at a time
+/// or one number per line. Values outside ASCII are printed as numbers in
+/// both modes.
+pub struct StdoutSink<W: Write = io::Stdout> {
+    writer: W,
+    ascii: bool,
+    line: String,
+}
+
+impl StdoutSink {
+    pub fn ascii() -> Self {
+        Self::from_writer(io::stdout(), true)
+    }
+
+    pub fn numbers() -> Self {
+        Self::from_writer(io::stdout(), false)
+    }
+}
+
+impl<W: Write> StdoutSink<W> {
+    pub fn from_writer(writer: W, ascii: bool) -> Self {
+        Self {
+            writer,
+            ascii,
+            line: String::new(),
+        }
+    }
+
+    fn flush_line(&mut self) {
+        if !self.line.is_empty() {
+            self.writer.write_all(self.line.as_bytes()).ok();
+            self.writer.flush().ok();
+            self.line.clear();
+        }
+    }
+}
+
+impl<W: Write> OutputSink for StdoutSink<W> {
+    fn send_output(&mut self, value: i64) {
+        match value {
+            0..=127 if self.ascii => {
+                self.line.push(value as u8 as char);
+                if value == b'\n' as i64 {
+                    self.flush_line();
+                }
+            }
+            _ => {
+                self.flush_line();
+                writeln!(self.writer, "{}", value).ok();
+            }
+        }
+    }
+}
+
+impl<W: Write> Drop for StdoutSink<W> {
+    fn drop(&mut self) {
+        self.flush_line();
+    }
+}
+
+#[test]
+fn it_reads_lines_as_ascii_or_numbers() {
+    let mut ascii = StdinSource::from_reader(&b"hi\nno"[..], true);
+    let read = std::iter::from_fn(|| ascii.next_input()).collect::<Vec<_>>();
+    assert_eq!(read, vec![104, 105, 10, 110, 111, 10]);
+
+    let mut numbers = StdinSource::from_reader(&b"5\n\n-3\n"[..], false);
+    let read = std::iter::from_fn(|| numbers.next_input()).collect::<Vec<_>>();
+    assert_eq!(read, vec![5, -3]);
+}
+
+#[test]
+fn it_writes_ascii_lines_and_large_values() {
+    let mut out = vec![];
+    {
+        let mut sink = StdoutSink::from_writer(&mut out, true);
+        for &value in &[111, 107, 10, 1_000, 104, 105] {
+            sink.send_output(value);
+        }
+    }
+    assert_eq!(String::from_utf8(out).unwrap(), "ok\n1000\nhi");
+}
@@ -0,0 +1,226 @@
+//! Undo history for running a machine backwards, see `Computer::set_journal`.
+//!
+//! Every executed instruction leaves an entry with what it overwrote, so
+//! the most recent ones can be undone one at a time. Only the last
+//! `capacity` entries are kept. Beyond those, a journal taking checkpoints
+//! restores the latest one before the target and replays forward from it,
+//! feeding back the inputs consumed since.
+
+use crate::memory::Memory;
+use num_bigint::BigInt;
+use std::collections::VecDeque;
+
+/// What one instruction changed, enough to undo it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub position: usize,
+    pub relative_base: i64,
+    /// The cells written and the values they held before, in order.
+    pub writes: Vec<(usize, i64)>,
+    pub inputs: Vec<i64>,
+    pub output: Option<i64>,
+    /// The value overwritten when it held more than 64 bits, `writes` has
+    /// it as 0.
+    pub big: Option<(usize, BigInt)>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+    pub step: u64,
+    pub registers: Memory,
+    pub position: usize,
+    pub relative_base: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Journal {
+    capacity: usize,
+    every: u64,
+    keep: usize,
+    pub(crate) steps: u64,
+    pub(crate) entries: VecDeque<Entry>,
+    pub(crate) checkpoints: VecDeque<Checkpoint>,
+    /// Inputs consumed and outputs produced since the oldest checkpoint,
+    /// with the step that did it.
+    pub(crate) inputs: VecDeque<(u64, i64)>,
+    pub(crate) outputs: VecDeque<(u64, i64)>,
+}
+
+impl Journal {
+    /// Keeps the last `capacity` instructions, each undone on its own.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            every: 0,
+            keep: 0,
+            steps: 0,
+            entries: VecDeque::new(),
+            checkpoints: VecDeque::new(),
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+        }
+    }
+
+    /// Also copies the whole machine every `every` instructions, keeping
+    /// the last `keep` copies to rewind further than the entries go.
+    pub fn checkpoints(mut self, every: u64, keep: usize) -> Self {
+        self.every = every;
+        self.keep = keep;
+        self
+    }
+
+    /// Instructions executed since the journal was set.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The earliest step the machine can be rewound to.
+    pub fn earliest(&self) -> u64 {
+        let undoable = self.steps - self.entries.len() as u64;
+        match self.checkpoints.front() {
+            Some(checkpoint) => checkpoint.step.min(undoable),
+            None => undoable,
+        }
+    }
+
+    /// The step that produced the `count`th most recent output still in
+    /// the journal.
+    pub fn output_step(&self, count: usize) -> Option<u64> {
+        let first = self.steps - self.entries.len() as u64;
+        let recent = self
+            .entries
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, entry)| entry.output.is_some())
+            .map(|(i, _)| first + i as u64);
+        let older = self
+            .outputs
+            .iter()
+            .rev()
+            .map(|&(step, _)| step)
+            .filter(|&step| step < first);
+        recent.chain(older).nth(count.checked_sub(1)?)
+    }
+
+    /// Whether checkpoints are taken and kept at all, without them there's
+    /// nothing to replay inputs into.
+    fn takes_checkpoints(&self) -> bool {
+        self.every > 0 && self.keep > 0
+    }
+
+    pub(crate) fn wants_checkpoint(&self) -> bool {
+        self.takes_checkpoints()
+            && self.steps.is_multiple_of(self.every)
+            && self.checkpoints.back().is_none_or(|c| c.step < self.steps)
+    }
+
+    pub(crate) fn checkpoint(&mut self, checkpoint: Checkpoint) {
+        self.checkpoints.push_back(checkpoint);
+        if self.checkpoints.len() > self.keep {
+            self.checkpoints.pop_front();
+            let oldest = self.checkpoints[0].step;
+            self.inputs.retain(|&(step, _)| step >= oldest);
+            self.outputs.retain(|&(step, _)| step >= oldest);
+        }
+    }
+
+    pub(crate) fn record(&mut self, entry: Entry) {
+        if self.takes_checkpoints() {
+            for &input in &entry.inputs {
+                self.inputs.push_back((self.steps, input));
+            }
+            if let Some(output) = entry.output {
+                self.outputs.push_back((self.steps, output));
+            }
+        }
+        self.steps += 1;
+        self.entries.push_back(entry);
+        if self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Takes back the latest entry.
+    pub(crate) fn pop(&mut self) -> Option<Entry> {
+        let entry = self.entries.pop_back()?;
+        self.steps -= 1;
+        self.forget_from(self.steps);
+        Some(entry)
+    }
+
+    /// Drops everything recorded at or after `step`, keeping a checkpoint
+    /// taken right at `step`.
+    pub(crate) fn forget_from(&mut self, step: u64) {
+        while self.checkpoints.back().is_some_and(|c| c.step > step) {
+            self.checkpoints.pop_back();
+        }
+        while self.inputs.back().is_some_and(|&(s, _)| s >= step) {
+            self.inputs.pop_back();
+        }
+        while self.outputs.back().is_some_and(|&(s, _)| s >= step) {
+            self.outputs.pop_back();
+        }
+    }
+
+    /// Forgets all history, used when the machine is restored from elsewhere.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new(self.capacity).checkpoints(self.every, self.keep);
+    }
+}
+
+#[test]
+fn it_keeps_only_the_last_entries() {
+    let entry = |output| Entry {
+        position: 0,
+        relative_base: 0,
+        writes: vec![],
+        inputs: vec![],
+        output,
+        big: None,
+    };
+    let mut journal = Journal::new(3);
+    for output in &[Some(1), None, Some(2), None, Some(3)] {
+        journal.record(entry(*output));
+    }
+    assert_eq!(journal.steps(), 5);
+    assert_eq!(journal.earliest(), 2);
+    assert_eq!(journal.output_step(1), Some(4));
+    assert_eq!(journal.output_step(2), Some(2));
+    assert_eq!(journal.output_step(3), None);
+    assert_eq!(journal.pop(), Some(entry(Some(3))));
+    assert_eq!(journal.steps(), 4);
+}
+
+#[test]
+fn it_bounds_the_history_it_replays() {
+    let entry = |input| Entry {
+        position: 0,
+        relative_base: 0,
+        writes: vec![],
+        inputs: vec![input],
+        output: Some(input),
+        big: None,
+    };
+    let mut journal = Journal::new(3).checkpoints(10, 0);
+    for input in 0..1_000 {
+        assert!(!journal.wants_checkpoint());
+        journal.record(entry(input));
+    }
+    assert!(journal.inputs.is_empty() && journal.outputs.is_empty());
+
+    let mut journal = Journal::new(3).checkpoints(10, 2);
+    for input in 0..1_000 {
+        if journal.wants_checkpoint() {
+            journal.checkpoint(Checkpoint {
+                step: journal.steps(),
+                registers: Memory::new(vec![]),
+                position: 0,
+                relative_base: 0,
+            });
+        }
+        journal.record(entry(input));
+    }
+    assert_eq!(journal.checkpoints.len(), 2);
+    assert!(journal.inputs.len() <= 20 && journal.outputs.len() <= 20);
+}
@@ -0,0 +1,52 @@
+//! The Intcode computer shared by every day that runs an Intcode program.
+
+mod arithmetic;
+mod ascii;
+mod asm;
+mod batch;
+mod cfg;
+mod computer;
+mod conformance;
+mod debugger;
+mod disasm;
+mod error;
+mod extension;
+mod instruction;
+mod io;
+mod journal;
+mod loader;
+mod memory;
+mod network;
+mod profile;
+mod snapshot;
+mod threaded;
+mod trace;
+mod translate;
+
+pub use arithmetic::Arithmetic;
+pub use ascii::{Ascii, Text};
+pub use asm::{assemble, AsmError};
+pub use batch::{run_batch, run_batch_with};
+pub use cfg::{basic_blocks, cfg_dot, Block, Terminator};
+pub use computer::{Computer, Event, State};
+pub use conformance::{corpus, generate, Case, Dialect, Disagreement, Outcome, Report};
+pub use debugger::Debugger;
+pub use disasm::{disassemble, find_code, listing, Line};
+pub use error::{ErrorKind, VmError};
+pub use extension::{Call, Direction, Effect, InstructionSet};
+pub use instruction::{Instruction, Mode, Opcode};
+pub use io::{InputSource, OutputSink, StdinSource, StdoutSink};
+pub use journal::Journal;
+pub use loader::{
+    input_to_registers, load_program, load_registers, parse_program, read_program, LoadError,
+    ParseError,
+};
+pub use memory::{Limits, Memory};
+pub use network::{Finish, Flow, IdlePolicy, Nat, Network, Node, Packet};
+pub use profile::Profiler;
+pub use snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+pub use threaded::ThreadedNetwork;
+pub use trace::{Record, Tracer};
+pub use translate::translate;
+
+pub use num_bigint::BigInt;
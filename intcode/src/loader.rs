@@ -0,0 +1,156 @@
+//! Reading programs from files, stdin or strings.
+//!
+//! A program is a list of integers separated by commas, whitespace or
+//! newlines, in any mix, with `#` starting a comment that runs to the end
+//! of the line:
+//!
+//! ```text
+//! # add the first two cells
+//! 1,0,0,3
+//! 99
+//! ```
+//!
+//! Saved machines, text or binary, are read by `Snapshot::load` and
+//! `Snapshot::read` instead.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// A token that isn't a number, at `offset` bytes into the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub token: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` at byte {} is not a number",
+            self.token, self.offset
+        )
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(error: ParseError) -> Self {
+        LoadError::Parse(error)
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+/// Runs of separators count as one, so blank lines and trailing commas are
+/// fine.
+pub fn parse_program(text: &str) -> Result<Vec<i64>, ParseError> {
+    let mut program = vec![];
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let code = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut rest = code;
+        while let Some(start) = rest.find(|c| !is_separator(c)) {
+            let end = rest[start..]
+                .find(is_separator)
+                .map_or(rest.len(), |end| start + end);
+            let token = &rest[start..end];
+            match token.parse() {
+                Ok(value) => program.push(value),
+                Err(_) => {
+                    return Err(ParseError {
+                        offset: offset + code.len() - rest.len() + start,
+                        token: token.to_string(),
+                    })
+                }
+            }
+            rest = &rest[end..];
+        }
+        offset += line.len();
+    }
+    Ok(program)
+}
+
+/// Reads all of `reader`, e.g. `io::stdin()`, as a program.
+pub fn read_program<R: Read>(mut reader: R) -> Result<Vec<i64>, LoadError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    Ok(parse_program(&text)?)
+}
+
+/// Reads the program at `path`, or stdin when `path` is `-`.
+pub fn load_program<P: AsRef<Path>>(path: P) -> Result<Vec<i64>, LoadError> {
+    let path = path.as_ref();
+    if path == Path::new("-") {
+        return read_program(io::stdin());
+    }
+    Ok(parse_program(&fs::read_to_string(path)?)?)
+}
+
+pub fn input_to_registers() -> Vec<i64> {
+    load_registers("input.txt")
+}
+
+/// `load_program` for drivers, panicking when the program can't be read.
+pub fn load_registers(filename: &str) -> Vec<i64> {
+    match load_program(filename) {
+        Ok(program) => program,
+        Err(error) => panic!("could not load {}: {}", filename, error),
+    }
+}
+
+#[test]
+fn it_reads_any_mix_of_separators_and_comments() {
+    let text = "# header\n1,0, 0,3\n\n2 3 11 0 # multiply\n\t99,30,40,50,\n";
+    assert_eq!(
+        parse_program(text).unwrap(),
+        vec![1, 0, 0, 3, 2, 3, 11, 0, 99, 30, 40, 50]
+    );
+    assert_eq!(parse_program("-1,+2\r\n").unwrap(), vec![-1, 2]);
+    assert_eq!(parse_program("# nothing\n").unwrap(), vec![]);
+    assert_eq!(read_program(&b"104,7,99"[..]).unwrap(), vec![104, 7, 99]);
+}
+
+#[test]
+fn it_reports_where_parsing_failed() {
+    let error = parse_program("1,2\n# 3,x\n4, 5x ,6").unwrap_err();
+    assert_eq!(error.offset, 13);
+    assert_eq!(error.token, "5x");
+    assert_eq!(error.to_string(), "`5x` at byte 13 is not a number");
+    assert!(matches!(
+        read_program(&b"1,99999999999999999999"[..]),
+        Err(LoadError::Parse(ParseError { offset: 2, .. }))
+    ));
+}
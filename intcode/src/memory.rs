@@ -0,0 +1,338 @@
+use crate::error::ErrorKind;
+use crate::instruction::Instruction;
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+const PAGE_BITS: usize = 7;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+/// Bounds on how far a program's memory may grow.
+///
+/// Addresses below `dense` live in pages that grow on write, anything
+/// above that up to `max_address` is kept in a sparse map of at most
+/// `sparse_cells` entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub dense: usize,
+    pub sparse_cells: usize,
+    pub max_address: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            dense: 1 << 20,
+            sparse_cells: 1 << 16,
+            max_address: i64::MAX as usize,
+        }
+    }
+}
+
+/// A run of cells and their decoded instructions.
+#[derive(Debug, Clone)]
+struct Page {
+    cells: [i64; PAGE_SIZE],
+    decoded: [Option<Instruction>; PAGE_SIZE],
+}
+
+impl Page {
+    fn zeroed() -> Self {
+        Self {
+            cells: [0; PAGE_SIZE],
+            decoded: [None; PAGE_SIZE],
+        }
+    }
+}
+
+fn split(address: usize) -> (usize, usize) {
+    (address >> PAGE_BITS, address & (PAGE_SIZE - 1))
+}
+
+/// A program's memory.
+///
+/// The dense region is split into pages shared between clones, a page is
+/// only copied when a clone writes to it. Cloning a machine to explore
+/// from it costs the pages it goes on to change rather than all of them.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    pages: Vec<Arc<Page>>,
+    /// Cells in the dense region, the rest of the last page reads as zero.
+    len: usize,
+    sparse: HashMap<usize, i64>,
+    /// Cells holding more than 64 bits, see `Arithmetic::Big`.
+    big: HashMap<usize, BigInt>,
+    limits: Limits,
+}
+
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self.iter().eq(other.iter())
+            && self.sparse == other.sparse
+            && self.big == other.big
+            && self.limits == other.limits
+    }
+}
+
+impl Eq for Memory {}
+
+impl Memory {
+    pub fn new(program: Vec<i64>) -> Self {
+        Self::with_limits(program, Limits::default())
+    }
+
+    pub fn with_limits(program: Vec<i64>, limits: Limits) -> Self {
+        let pages = program
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = Page::zeroed();
+                page.cells[..chunk.len()].copy_from_slice(chunk);
+                Arc::new(page)
+            })
+            .collect();
+        Self {
+            pages,
+            len: program.len(),
+            sparse: HashMap::new(),
+            big: HashMap::new(),
+            limits,
+        }
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Reads a cell, untouched cells read as zero.
+    pub fn get(&self, address: usize) -> Result<i64, ErrorKind> {
+        if address > self.limits.max_address {
+            return Err(ErrorKind::OutOfBounds(address));
+        }
+        if !self.big.is_empty() && self.big.contains_key(&address) {
+            return Err(ErrorKind::BigValue(address));
+        }
+        if address < self.len {
+            let (page, offset) = split(address);
+            return Ok(self.pages[page].cells[offset]);
+        }
+        Ok(self.sparse.get(&address).copied().unwrap_or(0))
+    }
+
+    /// Returns a writable cell, growing memory to hold it if needed.
+    pub fn get_mut(&mut self, address: usize) -> Result<&mut i64, ErrorKind> {
+        if address > self.limits.max_address {
+            return Err(ErrorKind::OutOfBounds(address));
+        }
+        if !self.big.is_empty() {
+            self.big.remove(&address);
+        }
+        if address < self.len || address < self.limits.dense {
+            if address >= self.len {
+                self.grow(address + 1);
+            }
+            let (page, offset) = split(address);
+            let page = Arc::make_mut(&mut self.pages[page]);
+            page.decoded[offset] = None;
+            return Ok(&mut page.cells[offset]);
+        }
+        if self.sparse.len() >= self.limits.sparse_cells && !self.sparse.contains_key(&address) {
+            return Err(ErrorKind::MemoryExhausted(address));
+        }
+        Ok(self.sparse.entry(address).or_insert(0))
+    }
+
+    fn grow(&mut self, len: usize) {
+        let pages = (len + PAGE_SIZE - 1) >> PAGE_BITS;
+        if pages > self.pages.len() {
+            self.pages.resize(pages, Arc::new(Page::zeroed()));
+        }
+        self.len = len;
+    }
+
+    /// Decodes the instruction at `address`, reusing the previous decode
+    /// unless the cell has been written since.
+    ///
+    /// Decodes are only remembered on pages this memory has to itself, so
+    /// a clone that only runs code goes on sharing every page.
+    pub fn decode(&mut self, address: usize) -> Result<Instruction, ErrorKind> {
+        let (page, offset) = split(address);
+        if address < self.len {
+            if let Some(instruction) = self.pages[page].decoded[offset] {
+                return Ok(instruction);
+            }
+        }
+        let instruction = Instruction::decode(self.get(address)?)?;
+        if address < self.len {
+            if let Some(page) = Arc::get_mut(&mut self.pages[page]) {
+                page.decoded[offset] = Some(instruction);
+            }
+        }
+        Ok(instruction)
+    }
+
+    pub fn set(&mut self, address: usize, value: i64) -> Result<(), ErrorKind> {
+        *self.get_mut(address)? = value;
+        Ok(())
+    }
+
+    /// Reads a cell whatever its size.
+    pub fn get_big(&self, address: usize) -> Result<BigInt, ErrorKind> {
+        match self.big.get(&address) {
+            Some(value) => Ok(value.clone()),
+            None => self.get(address).map(BigInt::from),
+        }
+    }
+
+    /// Writes a cell, keeping values that outgrow an `i64` on the side.
+    pub fn set_big(&mut self, address: usize, value: BigInt) -> Result<(), ErrorKind> {
+        match i64::try_from(&value) {
+            Ok(value) => self.set(address, value),
+            Err(_) => {
+                self.set(address, 0)?;
+                self.big.insert(address, value);
+                Ok(())
+            }
+        }
+    }
+
+    /// Cells in the dense region, the loaded program plus any growth.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The cells of the dense region in order.
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        self.pages
+            .iter()
+            .flat_map(|page| page.cells.iter().copied())
+            .take(self.len)
+    }
+
+    /// A copy of the dense region.
+    pub fn to_vec(&self) -> Vec<i64> {
+        self.iter().collect()
+    }
+
+    /// Pages also held by a clone.
+    pub fn shared_pages(&self) -> usize {
+        self.pages
+            .iter()
+            .filter(|page| Arc::strong_count(page) > 1)
+            .count()
+    }
+
+    /// Cells living above the dense region, in no particular order.
+    pub fn sparse_cells(&self) -> impl Iterator<Item = (usize, i64)> + '_ {
+        self.sparse
+            .iter()
+            .map(|(&address, &value)| (address, value))
+    }
+
+    /// Cells holding more than 64 bits, in no particular order.
+    pub fn big_cells(&self) -> impl Iterator<Item = (usize, &BigInt)> + '_ {
+        self.big.iter().map(|(&address, value)| (address, value))
+    }
+}
+
+#[test]
+fn it_reads_untouched_cells_as_zero() {
+    let memory = Memory::new(vec![1, 2, 3]);
+    assert_eq!(memory.get(1), Ok(2));
+    assert_eq!(memory.get(1_000_000_000), Ok(0));
+    assert_eq!(memory.to_vec(), [1, 2, 3]);
+}
+
+#[test]
+fn it_grows_on_write() {
+    let mut memory = Memory::new(vec![1, 2, 3]);
+    memory.set(5, 9).unwrap();
+    assert_eq!(memory.to_vec(), [1, 2, 3, 0, 0, 9]);
+    memory.set(1000, 4).unwrap();
+    assert_eq!(memory.len(), 1001);
+    assert_eq!(memory.get(999), Ok(0));
+    assert_eq!(memory.get(1000), Ok(4));
+}
+
+#[test]
+fn it_keeps_high_addresses_sparse() {
+    let limits = Limits {
+        dense: 16,
+        ..Limits::default()
+    };
+    let mut memory = Memory::with_limits(vec![], limits);
+    memory.set(1 << 40, 7).unwrap();
+    assert_eq!(memory.get(1 << 40), Ok(7));
+    assert!(memory.is_empty());
+}
+
+#[test]
+fn it_enforces_limits() {
+    let limits = Limits {
+        dense: 4,
+        sparse_cells: 1,
+        max_address: 100,
+    };
+    let mut memory = Memory::with_limits(vec![], limits);
+    assert_eq!(memory.set(101, 1), Err(ErrorKind::OutOfBounds(101)));
+    assert_eq!(memory.get(101), Err(ErrorKind::OutOfBounds(101)));
+    memory.set(50, 1).unwrap();
+    memory.set(50, 2).unwrap();
+    assert_eq!(memory.set(60, 1), Err(ErrorKind::MemoryExhausted(60)));
+}
+
+#[test]
+fn it_invalidates_decoded_instructions_on_write() {
+    use crate::instruction::Opcode;
+
+    let mut memory = Memory::new(vec![1, 0, 0, 0, 99]);
+    assert_eq!(memory.decode(0).unwrap().opcode, Opcode::Add);
+    memory.set(0, 2).unwrap();
+    assert_eq!(memory.decode(0).unwrap().opcode, Opcode::Mult);
+}
+
+#[test]
+fn it_copies_pages_on_write() {
+    let program = (0..1000).collect::<Vec<i64>>();
+    let mut memory = Memory::new(program.clone());
+    memory.decode(1).unwrap();
+    let mut clone = memory.clone();
+    let pages = 1000_usize.div_ceil(PAGE_SIZE);
+    assert_eq!(clone.shared_pages(), pages);
+
+    clone.set(300, -1).unwrap();
+    assert_eq!(clone.shared_pages(), pages - 1);
+    assert_eq!(memory.get(300), Ok(300));
+    assert_eq!(clone.get(300), Ok(-1));
+    assert_ne!(clone, memory);
+    clone.set(300, 300).unwrap();
+    assert_eq!(clone, memory);
+    assert_eq!(clone.to_vec(), program);
+}
+
+#[test]
+fn it_shares_pages_with_clones_that_only_run_code() {
+    use crate::computer::Computer;
+
+    // outputs its first cell, the rest of memory is never touched
+    let mut program = vec![4, 0, 99];
+    program.resize(1000, 0);
+    let base = Computer::new(program);
+    let mut clone = base.clone();
+    clone.run().unwrap();
+    assert_eq!(clone.outputs, vec![4]);
+    assert_eq!(
+        clone.registers.shared_pages(),
+        1000_usize.div_ceil(PAGE_SIZE)
+    );
+
+    // a page of its own still caches decodes
+    let mut memory = Memory::new(vec![1, 0, 0, 0, 99]);
+    memory.decode(0).unwrap();
+    assert!(memory.pages[0].decoded[0].is_some());
+}
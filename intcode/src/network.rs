@@ -0,0 +1,328 @@
+//! A deterministic network of Intcode machines exchanging 3-word packets.
+//!
+//! Machines take turns in address order. On its turn a machine with nothing
+//! queued reads -1, then runs until it waits for input again; every complete
+//! `address, x, y` triple it outputs is routed before the next machine runs.
+//! Addresses without a machine can be served by a `Node`, such as a `Nat`.
+
+use crate::computer::{Computer, State};
+use crate::error::VmError;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub from: i64,
+    pub to: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Whether a node or hook wants the network to keep going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+/// Something other than a machine listening on an address.
+pub trait Node {
+    fn receive(&mut self, packet: Packet) -> Flow;
+
+    /// Called once the network goes idle, a packet returned here is sent
+    /// with `from` set to this node's address.
+    fn idle(&mut self) -> Option<Packet> {
+        None
+    }
+}
+
+impl<F: FnMut(Packet) -> Flow> Node for F {
+    fn receive(&mut self, packet: Packet) -> Flow {
+        self(packet)
+    }
+}
+
+/// Remembers the last packet it received and sends it on to `target`
+/// whenever the network goes idle.
+pub struct Nat {
+    target: i64,
+    last: Option<Packet>,
+}
+
+impl Nat {
+    pub fn new(target: i64) -> Self {
+        Self { target, last: None }
+    }
+}
+
+impl Node for Nat {
+    fn receive(&mut self, packet: Packet) -> Flow {
+        self.last = Some(packet);
+        Flow::Continue
+    }
+
+    fn idle(&mut self) -> Option<Packet> {
+        let last = self.last?;
+        Some(Packet {
+            to: self.target,
+            ..last
+        })
+    }
+}
+
+/// When the network counts as idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlePolicy {
+    Never,
+    /// After this many rounds in a row where every machine read -1 and
+    /// sent nothing.
+    QuietRounds(usize),
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        IdlePolicy::QuietRounds(2)
+    }
+}
+
+/// Why `Network::run` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finish {
+    /// A node or hook returned `Flow::Stop`.
+    Stopped,
+    /// Every machine halted.
+    Halted,
+    /// The network went idle and no node had anything to send.
+    Idle,
+}
+
+type Hook<'a> = Box<dyn FnMut(&Packet) -> Flow + 'a>;
+
+struct Machine {
+    comp: Computer,
+    pending: Vec<i64>,
+}
+
+#[derive(Default)]
+pub struct Network<'a> {
+    machines: BTreeMap<i64, Machine>,
+    nodes: BTreeMap<i64, Box<dyn Node + 'a>>,
+    hooks: Vec<Hook<'a>>,
+    idle_policy: IdlePolicy,
+    dropped: Vec<Packet>,
+}
+
+impl<'a> Network<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a machine and hands it `address` as its first input.
+    pub fn add_machine(&mut self, address: i64, mut comp: Computer) {
+        comp.input(address);
+        self.machines.insert(
+            address,
+            Machine {
+                comp,
+                pending: vec![],
+            },
+        );
+    }
+
+    pub fn add_node<N: Node + 'a>(&mut self, address: i64, node: N) {
+        self.nodes.insert(address, Box::new(node));
+    }
+
+    /// Called with every packet before it is delivered, in the order they
+    /// are sent.
+    pub fn on_packet<F: FnMut(&Packet) -> Flow + 'a>(&mut self, hook: F) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn set_idle_policy(&mut self, idle_policy: IdlePolicy) {
+        self.idle_policy = idle_policy;
+    }
+
+    pub fn machine(&self, address: i64) -> Option<&Computer> {
+        self.machines.get(&address).map(|machine| &machine.comp)
+    }
+
+    /// Packets sent to addresses nobody listens on.
+    pub fn dropped(&self) -> &[Packet] {
+        &self.dropped
+    }
+
+    /// Delivers `packet` as if a machine had sent it.
+    pub fn send(&mut self, packet: Packet) -> Flow {
+        let mut flow = Flow::Continue;
+        for hook in &mut self.hooks {
+            if hook(&packet) == Flow::Stop {
+                flow = Flow::Stop;
+            }
+        }
+        if let Some(machine) = self.machines.get_mut(&packet.to) {
+            machine.comp.input(packet.x);
+            machine.comp.input(packet.y);
+        } else if let Some(node) = self.nodes.get_mut(&packet.to) {
+            if node.receive(packet) == Flow::Stop {
+                flow = Flow::Stop;
+            }
+        } else {
+            self.dropped.push(packet);
+        }
+        flow
+    }
+
+    /// Gives every running machine one turn, returning whether any of them
+    /// had input or sent something, or `None` if the network was stopped.
+    fn round(&mut self) -> Result<Option<bool>, VmError> {
+        let mut busy = false;
+        let addresses = self.machines.keys().copied().collect::<Vec<_>>();
+        for address in addresses {
+            let machine = self.machines.get_mut(&address).unwrap();
+            if machine.comp.state == State::Halted {
+                continue;
+            }
+            if machine.comp.inputs.is_empty() {
+                machine.comp.input(-1);
+            } else {
+                busy = true;
+            }
+            machine.comp.run()?;
+            machine.pending.extend(machine.comp.outputs.drain(..));
+
+            let complete = machine.pending.len() / 3 * 3;
+            let packets = machine.pending.drain(..complete).collect::<Vec<_>>();
+            for chunk in packets.chunks(3) {
+                busy = true;
+                let packet = Packet {
+                    from: address,
+                    to: chunk[0],
+                    x: chunk[1],
+                    y: chunk[2],
+                };
+                if self.send(packet) == Flow::Stop {
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(busy))
+    }
+
+    /// Runs rounds until a node or hook stops the network, every machine
+    /// halts, or it goes idle with nothing left to send.
+    pub fn run(&mut self) -> Result<Finish, VmError> {
+        let mut quiet = 0;
+        loop {
+            let busy = match self.round()? {
+                Some(busy) => busy,
+                None => return Ok(Finish::Stopped),
+            };
+            if self
+                .machines
+                .values()
+                .all(|machine| machine.comp.state == State::Halted)
+            {
+                return Ok(Finish::Halted);
+            }
+
+            quiet = if busy { 0 } else { quiet + 1 };
+            match self.idle_policy {
+                IdlePolicy::QuietRounds(rounds) if quiet >= rounds => quiet = 0,
+                _ => continue,
+            }
+
+            let mut woken = false;
+            let addresses = self.nodes.keys().copied().collect::<Vec<_>>();
+            for address in addresses {
+                if let Some(packet) = self.nodes.get_mut(&address).unwrap().idle() {
+                    woken = true;
+                    let packet = Packet {
+                        from: address,
+                        ..packet
+                    };
+                    if self.send(packet) == Flow::Stop {
+                        return Ok(Finish::Stopped);
+                    }
+                }
+            }
+            if !woken {
+                return Ok(Finish::Idle);
+            }
+        }
+    }
+}
+
+#[test]
+fn it_routes_packets_in_address_order() {
+    // reads its address then forwards every packet it gets to address + 1
+    let relay = vec![
+        3, 26, 1001, 26, 1, 27, 3, 28, 1008, 28, -1, 30, 1005, 30, 6, 3, 29, 4, 27, 4, 28, 4, 29,
+        1105, 1, 6, 0, 0, 0, 0, 0,
+    ];
+    let mut seen = vec![];
+    let mut received = None;
+    let mut network = Network::new();
+    for address in 0..3 {
+        network.add_machine(address, Computer::new(relay.clone()));
+    }
+    network.add_node(3, |packet: Packet| {
+        received = Some((packet.x, packet.y));
+        Flow::Stop
+    });
+    network.on_packet(|packet| {
+        seen.push((packet.from, packet.to));
+        Flow::Continue
+    });
+    network.send(Packet {
+        from: -1,
+        to: 0,
+        x: 7,
+        y: 8,
+    });
+    assert_eq!(network.run(), Ok(Finish::Stopped));
+    drop(network);
+
+    assert_eq!(seen, vec![(-1, 0), (0, 1), (1, 2), (2, 3)]);
+    assert_eq!(received, Some((7, 8)));
+}
+
+#[test]
+fn it_wakes_the_network_through_the_nat_when_idle() {
+    let relay = vec![
+        3, 26, 1001, 26, 1, 27, 3, 28, 1008, 28, -1, 30, 1005, 30, 6, 3, 29, 4, 27, 4, 28, 4, 29,
+        1105, 1, 6, 0, 0, 0, 0, 0,
+    ];
+    let mut network = Network::new();
+    network.add_machine(0, Computer::new(relay.clone()));
+    network.add_machine(1, Computer::new(relay.clone()));
+    let packet = Packet {
+        from: -1,
+        to: 0,
+        x: 1,
+        y: 2,
+    };
+    network.send(packet);
+    assert_eq!(network.run(), Ok(Finish::Idle));
+    assert_eq!(network.dropped().len(), 1);
+    assert_eq!(network.dropped()[0].to, 2);
+
+    let mut wakeups = 0;
+    let mut network = Network::new();
+    network.add_machine(0, Computer::new(relay.clone()));
+    network.add_machine(1, Computer::new(relay));
+    network.add_node(2, Nat::new(0));
+    network.on_packet(|packet| {
+        if packet.from == 2 {
+            wakeups += 1;
+        }
+        if wakeups == 3 {
+            Flow::Stop
+        } else {
+            Flow::Continue
+        }
+    });
+    network.send(packet);
+    assert_eq!(network.run(), Ok(Finish::Stopped));
+    drop(network);
+    assert_eq!(wakeups, 3);
+}
@@ -0,0 +1,222 @@
+//! Where a program spends its time, see `Computer::enable_profiler`.
+
+use crate::instruction::Opcode;
+use std::collections::HashMap;
+
+/// What one executed instruction touched.
+pub(crate) struct Access {
+    pub ip: usize,
+    pub opcode: Opcode,
+    /// Addresses read through position or relative parameters.
+    pub reads: Vec<usize>,
+    pub write: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Heat {
+    executions: u64,
+    reads: u64,
+    writes: u64,
+    /// The last instruction that started here.
+    opcode: Option<Opcode>,
+}
+
+/// Counts executions per address and per opcode, and reads and writes per
+/// address.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    program_len: usize,
+    heat: HashMap<usize, Heat>,
+    opcodes: [u64; Opcode::ALL.len()],
+    instructions: u64,
+    inputs: u64,
+}
+
+fn opcode_index(opcode: Opcode) -> usize {
+    Opcode::ALL.iter().position(|&o| o == opcode).unwrap()
+}
+
+impl Profiler {
+    /// A profiler for a program `program_len` words long, used to work out
+    /// coverage.
+    pub fn new(program_len: usize) -> Self {
+        Self {
+            program_len,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn count(&mut self, access: Access) {
+        self.instructions += 1;
+        self.opcodes[opcode_index(access.opcode)] += 1;
+        if access.opcode == Opcode::Input {
+            self.inputs += 1;
+        }
+        let start = self.heat.entry(access.ip).or_default();
+        start.executions += 1;
+        start.opcode = Some(access.opcode);
+        for address in access.reads {
+            self.heat.entry(address).or_default().reads += 1;
+        }
+        if let Some(address) = access.write {
+            self.heat.entry(address).or_default().writes += 1;
+        }
+    }
+
+    /// Instructions executed in total.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn inputs(&self) -> u64 {
+        self.inputs
+    }
+
+    /// Times an instruction started at `address`.
+    pub fn executions(&self, address: usize) -> u64 {
+        self.heat.get(&address).map_or(0, |heat| heat.executions)
+    }
+
+    pub fn opcode_count(&self, opcode: Opcode) -> u64 {
+        self.opcodes[opcode_index(opcode)]
+    }
+
+    pub fn reads(&self, address: usize) -> u64 {
+        self.heat.get(&address).map_or(0, |heat| heat.reads)
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.heat.get(&address).map_or(0, |heat| heat.writes)
+    }
+
+    /// The `n` addresses most instructions started at, hottest first.
+    pub fn hot_addresses(&self, n: usize) -> Vec<(usize, u64)> {
+        self.hottest(n, |heat| heat.executions)
+    }
+
+    /// The `n` addresses read or written most, hottest first.
+    pub fn hot_memory(&self, n: usize) -> Vec<(usize, u64)> {
+        self.hottest(n, |heat| heat.reads + heat.writes)
+    }
+
+    fn hottest<F: Fn(&Heat) -> u64>(&self, n: usize, count: F) -> Vec<(usize, u64)> {
+        let mut hot = self
+            .heat
+            .iter()
+            .map(|(&address, heat)| (address, count(heat)))
+            .filter(|&(_, count)| count > 0)
+            .collect::<Vec<_>>();
+        hot.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.truncate(n);
+        hot
+    }
+
+    /// How many words of the loaded program were part of an executed
+    /// instruction, out of its length.
+    pub fn coverage(&self) -> (usize, usize) {
+        let mut covered = vec![false; self.program_len];
+        for (&address, heat) in &self.heat {
+            if let Some(opcode) = heat.opcode {
+                let end = (address + 1 + opcode.arity()).min(self.program_len);
+                for word in covered.iter_mut().take(end).skip(address) {
+                    *word = true;
+                }
+            }
+        }
+        (covered.iter().filter(|&&c| c).count(), self.program_len)
+    }
+
+    /// `None` until the program has read an input.
+    pub fn instructions_per_input(&self) -> Option<f64> {
+        if self.inputs == 0 {
+            None
+        } else {
+            Some(self.instructions as f64 / self.inputs as f64)
+        }
+    }
+
+    /// A plain text summary listing the `top` hottest addresses.
+    pub fn report(&self, top: usize) -> String {
+        let (covered, len) = self.coverage();
+        let percent = if len == 0 {
+            0.0
+        } else {
+            covered as f64 * 100.0 / len as f64
+        };
+        let mut report = format!("instructions {}\n", self.instructions);
+        report += &format!("inputs {}", self.inputs);
+        if let Some(per_input) = self.instructions_per_input() {
+            report += &format!(" ({:.1} instructions per input)", per_input);
+        }
+        report += &format!("\ncoverage {}/{} ({:.1}%)\n", covered, len, percent);
+
+        report += "opcodes\n";
+        for &opcode in Opcode::ALL.iter() {
+            let count = self.opcode_count(opcode);
+            if count > 0 {
+                report += &format!("  {:<4}{:>12}\n", opcode.mnemonic(), count);
+            }
+        }
+        report += "hot addresses\n";
+        for (address, count) in self.hot_addresses(top) {
+            let mnemonic = self.heat[&address].opcode.map_or("", Opcode::mnemonic);
+            report += &format!("  {:04}  {:<4}{:>12}\n", address, mnemonic, count);
+        }
+        report += "hot memory\n";
+        for (address, _) in self.hot_memory(top) {
+            let heat = &self.heat[&address];
+            report += &format!(
+                "  {:04}  {:>12} reads {:>12} writes\n",
+                address, heat.reads, heat.writes
+            );
+        }
+        report
+    }
+
+    /// Every touched address as `address,opcode,executions,reads,writes`,
+    /// in address order.
+    pub fn to_csv(&self) -> String {
+        let mut addresses = self.heat.keys().copied().collect::<Vec<_>>();
+        addresses.sort_unstable();
+        let mut csv = "address,opcode,executions,reads,writes\n".to_string();
+        for address in addresses {
+            let heat = &self.heat[&address];
+            csv += &format!(
+                "{},{},{},{},{}\n",
+                address,
+                heat.opcode.map_or("", Opcode::mnemonic),
+                heat.executions,
+                heat.reads,
+                heat.writes
+            );
+        }
+        csv
+    }
+}
+
+#[test]
+fn it_ranks_addresses_and_measures_coverage() {
+    let mut profiler = Profiler::new(10);
+    for _ in 0..3 {
+        profiler.count(Access {
+            ip: 4,
+            opcode: Opcode::Add,
+            reads: vec![8, 8],
+            write: Some(9),
+        });
+    }
+    profiler.count(Access {
+        ip: 0,
+        opcode: Opcode::Input,
+        reads: vec![],
+        write: Some(9),
+    });
+
+    assert_eq!(profiler.hot_addresses(5), vec![(4, 3), (0, 1)]);
+    assert_eq!(profiler.hot_memory(1), vec![(8, 6)]);
+    assert_eq!(profiler.writes(9), 4);
+    assert_eq!(profiler.opcode_count(Opcode::Add), 3);
+    assert_eq!(profiler.coverage(), (6, 10));
+    assert_eq!(profiler.instructions_per_input(), Some(4.0));
+    assert!(profiler.to_csv().contains("\n4,ADD,3,0,0\n"));
+}
@@ -0,0 +1,691 @@
+//! Whole machine snapshots, see `Computer::snapshot`.
+//!
+//! The file format is line based text:
+//!
+//! ```text
+//! intcode snapshot 2
+//! limits 1048576 65536 9223372036854775807
+//! position 1234
+//! relative_base 4789
+//! state Waiting
+//! arithmetic Big
+//! inputs 110,10
+//! outputs
+//! memory 109,4789,21101,...
+//! sparse 2000000=5
+//! big 17=-340282366920938463463374607431768211456
+//! ```
+//!
+//! The `big` line only appears when cells have outgrown 64 bits, see
+//! `Arithmetic::Big`.
+//!
+//! Version 1 is the same without the `arithmetic` and `big` lines, read as
+//! wrapping arithmetic.
+//!
+//! A file without the header is read as version 0, a bare memory dump
+//! starting at address 0 in any format `parse_program` reads.
+//!
+//! `to_bytes` writes the same fields in a compact binary form: the magic
+//! bytes `\x7fICS`, a version byte, then every number as a LEB128 varint,
+//! zigzag encoded when it can be negative. Lists are prefixed by their
+//! length and big cells are written as their two's complement bytes.
+//! Binary version 1 has no arithmetic byte after the state.
+//! `load` and `read` tell the two forms apart by the magic bytes.
+
+use crate::arithmetic::Arithmetic;
+use crate::computer::State;
+use crate::loader::parse_program;
+use crate::memory::{Limits, Memory};
+use num_bigint::BigInt;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+const HEADER: &str = "intcode snapshot";
+
+const MAGIC: &[u8] = b"\x7fICS";
+
+const ARITHMETICS: [Arithmetic; 3] = [Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Big];
+
+const STATES: [State; 5] = [
+    State::Operating,
+    State::Halted,
+    State::Waiting,
+    State::OutOfFuel,
+    State::Cancelled,
+];
+
+/// Everything needed to resume a `Computer` where it left off.
+///
+/// Fuel, cancel flags and tracers belong to whoever is running the machine
+/// and aren't captured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub registers: Memory,
+    pub position: usize,
+    pub relative_base: i64,
+    pub inputs: VecDeque<i64>,
+    pub outputs: VecDeque<i64>,
+    pub state: State,
+    pub arithmetic: Arithmetic,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    Malformed {
+        line: usize,
+        message: String,
+    },
+    /// A binary snapshot that doesn't decode, `offset` bytes in.
+    Corrupt {
+        offset: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "{}", error),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            SnapshotError::Malformed { line, message } => {
+                write!(f, "malformed snapshot at line {}: {}", line, message)
+            }
+            SnapshotError::Corrupt { offset, message } => {
+                write!(f, "corrupt snapshot at byte {}: {}", offset, message)
+            }
+        }
+    }
+}
+
+impl error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+fn malformed<T>(line: usize, message: String) -> Result<T, SnapshotError> {
+    Err(SnapshotError::Malformed { line, message })
+}
+
+fn join<'a, I: IntoIterator<Item = &'a i64>>(values: I) -> String {
+    values
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_number<T: FromStr>(line: usize, text: &str) -> Result<T, SnapshotError> {
+    match text.trim().parse() {
+        Ok(value) => Ok(value),
+        Err(_) => malformed(line, format!("`{}` is not a number", text.trim())),
+    }
+}
+
+fn parse_list(line: usize, text: &str) -> Result<Vec<i64>, SnapshotError> {
+    text.split(',')
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| parse_number(line, value))
+        .collect()
+}
+
+/// `address=value` pairs separated by commas.
+fn parse_cells<T: FromStr>(
+    line: usize,
+    key: &str,
+    text: &str,
+) -> Result<Vec<(usize, usize, T)>, SnapshotError> {
+    let mut cells = vec![];
+    for cell in text.split(',').filter(|cell| !cell.trim().is_empty()) {
+        match cell.find('=') {
+            Some(split) => cells.push((
+                line,
+                parse_number(line, &cell[..split])?,
+                parse_number(line, &cell[split + 1..])?,
+            )),
+            None => return malformed(line, format!("bad {} cell `{}`", key, cell)),
+        }
+    }
+    Ok(cells)
+}
+
+fn parse_state(line: usize, text: &str) -> Result<State, SnapshotError> {
+    match text {
+        "Operating" => Ok(State::Operating),
+        "Halted" => Ok(State::Halted),
+        "Waiting" => Ok(State::Waiting),
+        "OutOfFuel" => Ok(State::OutOfFuel),
+        "Cancelled" => Ok(State::Cancelled),
+        _ => malformed(line, format!("unknown state `{}`", text)),
+    }
+}
+
+fn parse_arithmetic(line: usize, text: &str) -> Result<Arithmetic, SnapshotError> {
+    match text {
+        "Wrapping" => Ok(Arithmetic::Wrapping),
+        "Checked" => Ok(Arithmetic::Checked),
+        "Big" => Ok(Arithmetic::Big),
+        _ => malformed(line, format!("unknown arithmetic `{}`", text)),
+    }
+}
+
+fn corrupt<T>(offset: usize, message: &str) -> Result<T, SnapshotError> {
+    Err(SnapshotError::Corrupt {
+        offset,
+        message: message.to_string(),
+    })
+}
+
+fn put_unsigned(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn put_signed(bytes: &mut Vec<u8>, value: i64) {
+    put_unsigned(bytes, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+/// A cursor over a binary snapshot.
+struct Bytes<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() - self.offset < len {
+            return corrupt(self.bytes.len(), "unexpected end");
+        }
+        self.offset += len;
+        Ok(&self.bytes[self.offset - len..self.offset])
+    }
+
+    fn byte(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn unsigned(&mut self) -> Result<u64, SnapshotError> {
+        let start = self.offset;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        corrupt(start, "varint longer than 64 bits")
+    }
+
+    fn signed(&mut self) -> Result<i64, SnapshotError> {
+        let value = self.unsigned()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn address(&mut self) -> Result<usize, SnapshotError> {
+        let start = self.offset;
+        match usize::try_from(self.unsigned()?) {
+            Ok(address) => Ok(address),
+            Err(_) => corrupt(start, "number too large"),
+        }
+    }
+
+    /// A length prefix, no more than the bytes left since every item
+    /// takes at least one.
+    fn len(&mut self) -> Result<usize, SnapshotError> {
+        let start = self.offset;
+        let len = self.address()?;
+        if len > self.bytes.len() - self.offset {
+            return corrupt(start, "length past the end");
+        }
+        Ok(len)
+    }
+
+    fn values(&mut self) -> Result<Vec<i64>, SnapshotError> {
+        (0..self.len()?).map(|_| self.signed()).collect()
+    }
+}
+
+impl Snapshot {
+    /// A machine about to run `program` from the start.
+    fn fresh(program: Vec<i64>) -> Self {
+        Self {
+            registers: Memory::new(program),
+            position: 0,
+            relative_base: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            state: State::Operating,
+            arithmetic: Arithmetic::Wrapping,
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn save_binary<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Reads a text or binary snapshot from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        Self::from_any(&fs::read(path)?)
+    }
+
+    /// Reads a text or binary snapshot from `reader`, e.g. `io::stdin()`.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        Self::from_any(&bytes)
+    }
+
+    fn from_any(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if bytes.starts_with(MAGIC) {
+            return Self::from_bytes(bytes);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text.parse(),
+            Err(error) => Err(SnapshotError::Corrupt {
+                offset: error.valid_up_to(),
+                message: "neither text nor a binary snapshot".to_string(),
+            }),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let limits = self.registers.limits();
+        let mut sparse = self.registers.sparse_cells().collect::<Vec<_>>();
+        sparse.sort_unstable();
+        let mut big = self.registers.big_cells().collect::<Vec<_>>();
+        big.sort_unstable();
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(SNAPSHOT_VERSION as u8);
+        for &value in &[limits.dense, limits.sparse_cells, limits.max_address] {
+            put_unsigned(&mut bytes, value as u64);
+        }
+        put_unsigned(&mut bytes, self.position as u64);
+        put_signed(&mut bytes, self.relative_base);
+        bytes.push(STATES.iter().position(|s| *s == self.state).unwrap() as u8);
+        bytes.push(
+            ARITHMETICS
+                .iter()
+                .position(|&a| a == self.arithmetic)
+                .unwrap() as u8,
+        );
+        for values in &[&self.inputs, &self.outputs] {
+            put_unsigned(&mut bytes, values.len() as u64);
+            values
+                .iter()
+                .for_each(|&value| put_signed(&mut bytes, value));
+        }
+        let memory = self.registers.to_vec();
+        put_unsigned(&mut bytes, memory.len() as u64);
+        memory
+            .iter()
+            .for_each(|&value| put_signed(&mut bytes, value));
+        put_unsigned(&mut bytes, sparse.len() as u64);
+        for (address, value) in sparse {
+            put_unsigned(&mut bytes, address as u64);
+            put_signed(&mut bytes, value);
+        }
+        put_unsigned(&mut bytes, big.len() as u64);
+        for (address, value) in big {
+            let value = value.to_signed_bytes_le();
+            put_unsigned(&mut bytes, address as u64);
+            put_unsigned(&mut bytes, value.len() as u64);
+            bytes.extend(value);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if !bytes.starts_with(MAGIC) {
+            return corrupt(0, "missing magic bytes");
+        }
+        let mut reader = Bytes {
+            bytes,
+            offset: MAGIC.len(),
+        };
+        let version = u32::from(reader.byte()?);
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let limits = Limits {
+            dense: reader.address()?,
+            sparse_cells: reader.address()?,
+            max_address: reader.address()?,
+        };
+        let position = reader.address()?;
+        let relative_base = reader.signed()?;
+        let at = reader.offset;
+        let state = match STATES.get(reader.byte()? as usize) {
+            Some(state) => state.clone(),
+            None => return corrupt(at, "unknown state"),
+        };
+        let arithmetic = if version >= 2 {
+            let at = reader.offset;
+            match ARITHMETICS.get(reader.byte()? as usize) {
+                Some(&arithmetic) => arithmetic,
+                None => return corrupt(at, "unknown arithmetic"),
+            }
+        } else {
+            Arithmetic::Wrapping
+        };
+        let inputs = reader.values()?.into();
+        let outputs = reader.values()?.into();
+        let mut registers = Memory::with_limits(reader.values()?, limits);
+        for _ in 0..reader.len()? {
+            let at = reader.offset;
+            let (address, value) = (reader.address()?, reader.signed()?);
+            if let Err(kind) = registers.set(address, value) {
+                return corrupt(at, &kind.to_string());
+            }
+        }
+        for _ in 0..reader.len()? {
+            let at = reader.offset;
+            let address = reader.address()?;
+            let len = reader.len()?;
+            let value = BigInt::from_signed_bytes_le(reader.take(len)?);
+            if let Err(kind) = registers.set_big(address, value) {
+                return corrupt(at, &kind.to_string());
+            }
+        }
+        if reader.offset != bytes.len() {
+            return corrupt(reader.offset, "trailing bytes");
+        }
+        Ok(Self {
+            registers,
+            position,
+            relative_base,
+            inputs,
+            outputs,
+            state,
+            arithmetic,
+        })
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limits = self.registers.limits();
+        let mut sparse = self.registers.sparse_cells().collect::<Vec<_>>();
+        sparse.sort_unstable();
+        let sparse = sparse
+            .iter()
+            .map(|(address, value)| format!("{}={}", address, value))
+            .collect::<Vec<_>>();
+
+        let mut big = self.registers.big_cells().collect::<Vec<_>>();
+        big.sort_unstable();
+        let big = big
+            .iter()
+            .map(|(address, value)| format!("{}={}", address, value))
+            .collect::<Vec<_>>();
+
+        writeln!(f, "{} {}", HEADER, SNAPSHOT_VERSION)?;
+        writeln!(
+            f,
+            "limits {} {} {}",
+            limits.dense, limits.sparse_cells, limits.max_address
+        )?;
+        writeln!(f, "position {}", self.position)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        writeln!(f, "state {:?}", self.state)?;
+        writeln!(f, "arithmetic {:?}", self.arithmetic)?;
+        for (key, values) in &[
+            ("inputs", join(&self.inputs)),
+            ("outputs", join(&self.outputs)),
+            ("memory", join(&self.registers.to_vec())),
+            ("sparse", sparse.join(",")),
+        ] {
+            if values.is_empty() {
+                writeln!(f, "{}", key)?;
+            } else {
+                writeln!(f, "{} {}", key, values)?;
+            }
+        }
+        if !big.is_empty() {
+            writeln!(f, "big {}", big.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = SnapshotError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let version = match lines.next() {
+            Some((n, line)) if line.starts_with(HEADER) => {
+                parse_number::<u32>(n, &line[HEADER.len()..])?
+            }
+            _ => {
+                return match parse_program(text) {
+                    Ok(program) => Ok(Self::fresh(program)),
+                    Err(error) => {
+                        let line = text[..error.offset].matches('\n').count() + 1;
+                        malformed(line, error.to_string())
+                    }
+                }
+            }
+        };
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut snapshot = Self::fresh(vec![]);
+        let mut limits = Limits::default();
+        let mut memory = vec![];
+        let mut sparse = vec![];
+        let mut big = vec![];
+        for (n, line) in lines {
+            let (key, value) = match line.find(' ') {
+                Some(split) => (&line[..split], &line[split + 1..]),
+                None => (line, ""),
+            };
+            match key {
+                "limits" => {
+                    let values = value.split_whitespace().collect::<Vec<_>>();
+                    if values.len() != 3 {
+                        return malformed(n, "limits needs three values".to_string());
+                    }
+                    limits = Limits {
+                        dense: parse_number(n, values[0])?,
+                        sparse_cells: parse_number(n, values[1])?,
+                        max_address: parse_number(n, values[2])?,
+                    };
+                }
+                "position" => snapshot.position = parse_number(n, value)?,
+                "relative_base" => snapshot.relative_base = parse_number(n, value)?,
+                "state" => snapshot.state = parse_state(n, value.trim())?,
+                "inputs" => snapshot.inputs = parse_list(n, value)?.into(),
+                "outputs" => snapshot.outputs = parse_list(n, value)?.into(),
+                "memory" => memory = parse_list(n, value)?,
+                "sparse" => sparse.extend(parse_cells::<i64>(n, key, value)?),
+                "arithmetic" if version >= 2 => {
+                    snapshot.arithmetic = parse_arithmetic(n, value.trim())?
+                }
+                "big" if version >= 2 => big.extend(parse_cells::<BigInt>(n, key, value)?),
+                "" => {}
+                _ => return malformed(n, format!("unknown field `{}`", key)),
+            }
+        }
+
+        snapshot.registers = Memory::with_limits(memory, limits);
+        for (n, address, value) in sparse {
+            if let Err(kind) = snapshot.registers.set(address, value) {
+                return malformed(n, kind.to_string());
+            }
+        }
+        for (n, address, value) in big {
+            if let Err(kind) = snapshot.registers.set_big(address, value) {
+                return malformed(n, kind.to_string());
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+#[test]
+fn it_round_trips_through_text() {
+    let mut registers = Memory::new(vec![3, 0, 99]);
+    registers.set(5_000_000, -7).unwrap();
+    let snapshot = Snapshot {
+        registers,
+        position: 2,
+        relative_base: -3,
+        inputs: vec![1, 2].into(),
+        outputs: vec![].into(),
+        state: State::Waiting,
+        arithmetic: Arithmetic::Wrapping,
+    };
+    assert_eq!(snapshot.to_string().parse::<Snapshot>().unwrap(), snapshot);
+
+    let mut big = snapshot;
+    big.arithmetic = Arithmetic::Big;
+    big.registers
+        .set_big(1, BigInt::from(i64::MIN) * 3)
+        .unwrap();
+    let text = big.to_string();
+    assert!(text.starts_with("intcode snapshot 2\n"));
+    assert!(text.contains("\narithmetic Big\n"));
+    assert_eq!(text.parse::<Snapshot>().unwrap(), big);
+}
+
+#[test]
+fn it_reads_version_1() {
+    let text = "intcode snapshot 1
+limits 1048576 65536 9223372036854775807
+position 2
+relative_base -3
+state Waiting
+inputs 1,2
+outputs
+memory 3,0,99
+sparse 5000000=-7
+";
+    let snapshot = text.parse::<Snapshot>().unwrap();
+    assert_eq!(snapshot.registers.to_vec(), [3, 0, 99]);
+    assert_eq!(snapshot.registers.get(5_000_000), Ok(-7));
+    assert_eq!(snapshot.position, 2);
+    assert_eq!(snapshot.relative_base, -3);
+    assert_eq!(snapshot.inputs, vec![1, 2]);
+    assert_eq!(snapshot.state, State::Waiting);
+    assert_eq!(snapshot.arithmetic, Arithmetic::Wrapping);
+    assert!(matches!(
+        format!("{}big 1=0\n", text).parse::<Snapshot>(),
+        Err(SnapshotError::Malformed { line: 10, .. })
+    ));
+}
+
+#[test]
+fn it_reads_bare_memory_dumps_as_version_0() {
+    let snapshot = "1,2,3\n".parse::<Snapshot>().unwrap();
+    assert_eq!(snapshot.registers.to_vec(), [1, 2, 3]);
+    assert_eq!(snapshot.position, 0);
+}
+
+#[test]
+fn it_rejects_unknown_versions_and_fields() {
+    assert!(matches!(
+        "intcode snapshot 9\n".parse::<Snapshot>(),
+        Err(SnapshotError::UnsupportedVersion(9))
+    ));
+    assert!(matches!(
+        "intcode snapshot 2\nposition x\n".parse::<Snapshot>(),
+        Err(SnapshotError::Malformed { line: 2, .. })
+    ));
+    assert!(matches!(
+        "intcode snapshot 2\nregisters 1\n".parse::<Snapshot>(),
+        Err(SnapshotError::Malformed { line: 2, .. })
+    ));
+}
+
+#[test]
+fn it_round_trips_through_bytes() {
+    let mut registers = Memory::new(vec![3, 0, 99, i64::MIN, i64::MAX]);
+    registers.set(5_000_000, -7).unwrap();
+    registers.set_big(1, BigInt::from(i64::MAX) * 4).unwrap();
+    let snapshot = Snapshot {
+        registers,
+        position: 2,
+        relative_base: -3,
+        inputs: vec![1, -200].into(),
+        outputs: vec![300].into(),
+        state: State::OutOfFuel,
+        arithmetic: Arithmetic::Big,
+    };
+    let bytes = snapshot.to_bytes();
+    assert_eq!(bytes[4], 2);
+    assert!(bytes.len() < snapshot.to_string().len() / 2);
+    assert_eq!(Snapshot::from_bytes(&bytes).unwrap(), snapshot);
+    assert_eq!(Snapshot::read(&bytes[..]).unwrap(), snapshot);
+    assert_eq!(
+        Snapshot::read(snapshot.to_string().as_bytes()).unwrap(),
+        snapshot
+    );
+
+    assert!(matches!(
+        Snapshot::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(SnapshotError::Corrupt { .. })
+    ));
+    // after the magic, the version, the default limits, position and
+    // relative base
+    let mut bad_state = bytes.clone();
+    bad_state[22] = 9;
+    assert!(matches!(
+        Snapshot::from_bytes(&bad_state),
+        Err(SnapshotError::Corrupt { offset: 22, .. })
+    ));
+    let mut bad_arithmetic = bytes.clone();
+    bad_arithmetic[23] = 3;
+    assert!(matches!(
+        Snapshot::from_bytes(&bad_arithmetic),
+        Err(SnapshotError::Corrupt { offset: 23, .. })
+    ));
+
+    // version 1 is the same without the arithmetic byte
+    let mut version_1 = bytes.clone();
+    version_1[4] = 1;
+    version_1.remove(23);
+    let mut wrapping = snapshot.clone();
+    wrapping.arithmetic = Arithmetic::Wrapping;
+    assert_eq!(Snapshot::from_bytes(&version_1).unwrap(), wrapping);
+    let mut version_3 = bytes;
+    version_3[4] = 3;
+    assert!(matches!(
+        Snapshot::from_bytes(&version_3),
+        Err(SnapshotError::UnsupportedVersion(3))
+    ));
+}
+
+#[test]
+fn it_reads_commented_memory_dumps() {
+    let snapshot = "# day 2\n1 0 0 0\n99\n".parse::<Snapshot>().unwrap();
+    assert_eq!(snapshot.registers.to_vec(), [1, 0, 0, 0, 99]);
+    assert!(matches!(
+        "1,2\n3,x\n".parse::<Snapshot>(),
+        Err(SnapshotError::Malformed { line: 2, .. })
+    ));
+}
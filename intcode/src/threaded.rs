@@ -0,0 +1,328 @@
+//! The `Network` with every machine on its own thread.
+//!
+//! A machine reading from an empty mailbox gets -1 once, the next read
+//! blocks until a packet arrives. The calling thread routes packets and
+//! runs nodes and hooks. The network is idle when every machine is blocked
+//! on an empty mailbox with nothing left to route, and it shuts down when a
+//! machine halts or faults, a node or hook stops it, or it goes idle with
+//! nothing for the nodes to send.
+
+use crate::computer::{Computer, State};
+use crate::error::VmError;
+use crate::io::{InputSource, OutputSink};
+use crate::network::{Finish, Flow, Node, Packet};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+type Hook<'a> = Box<dyn FnMut(&Packet) -> Flow + 'a>;
+
+#[derive(Default)]
+struct Shared {
+    mailboxes: Vec<VecDeque<i64>>,
+    blocked: Vec<bool>,
+    finished: Vec<bool>,
+    outbox: VecDeque<Packet>,
+    halted: bool,
+    error: Option<VmError>,
+    shutdown: bool,
+}
+
+impl Shared {
+    fn idle(&self) -> bool {
+        self.outbox.is_empty()
+            && self.mailboxes.iter().all(VecDeque::is_empty)
+            && (0..self.blocked.len()).all(|i| self.blocked[i] || self.finished[i])
+    }
+}
+
+#[derive(Default)]
+struct Switch {
+    shared: Mutex<Shared>,
+    machines: Condvar,
+    supervisor: Condvar,
+}
+
+struct Mailbox<'s> {
+    switch: &'s Switch,
+    slot: usize,
+    polled: bool,
+}
+
+impl InputSource for Mailbox<'_> {
+    fn next_input(&mut self) -> Option<i64> {
+        let mut shared = self.switch.shared.lock().unwrap();
+        loop {
+            if shared.shutdown {
+                return None;
+            }
+            if let Some(value) = shared.mailboxes[self.slot].pop_front() {
+                self.polled = false;
+                return Some(value);
+            }
+            if !self.polled {
+                self.polled = true;
+                return Some(-1);
+            }
+            shared.blocked[self.slot] = true;
+            self.switch.supervisor.notify_one();
+            shared = self.switch.machines.wait(shared).unwrap();
+            shared.blocked[self.slot] = false;
+        }
+    }
+}
+
+struct Outbox<'s> {
+    switch: &'s Switch,
+    address: i64,
+    pending: Vec<i64>,
+}
+
+impl OutputSink for Outbox<'_> {
+    fn send_output(&mut self, value: i64) {
+        self.pending.push(value);
+        if let [to, x, y] = self.pending[..] {
+            self.pending.clear();
+            let mut shared = self.switch.shared.lock().unwrap();
+            shared.outbox.push_back(Packet {
+                from: self.address,
+                to,
+                x,
+                y,
+            });
+            self.switch.supervisor.notify_one();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ThreadedNetwork<'a> {
+    machines: BTreeMap<i64, Computer>,
+    nodes: BTreeMap<i64, Box<dyn Node + 'a>>,
+    hooks: Vec<Hook<'a>>,
+    outbox: Vec<Packet>,
+    dropped: Vec<Packet>,
+}
+
+impl<'a> ThreadedNetwork<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a machine, it reads `address` as its first input.
+    pub fn add_machine(&mut self, address: i64, comp: Computer) {
+        self.machines.insert(address, comp);
+    }
+
+    pub fn add_node<N: Node + 'a>(&mut self, address: i64, node: N) {
+        self.nodes.insert(address, Box::new(node));
+    }
+
+    /// Called with every packet before it is delivered.
+    pub fn on_packet<F: FnMut(&Packet) -> Flow + 'a>(&mut self, hook: F) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Packets sent to addresses nobody listens on.
+    pub fn dropped(&self) -> &[Packet] {
+        &self.dropped
+    }
+
+    /// Queues `packet` to be delivered once the network starts.
+    pub fn send(&mut self, packet: Packet) {
+        self.outbox.push(packet);
+    }
+
+    fn route(&mut self, switch: &Switch, slots: &BTreeMap<i64, usize>, packet: Packet) -> Flow {
+        let mut flow = Flow::Continue;
+        for hook in &mut self.hooks {
+            if hook(&packet) == Flow::Stop {
+                flow = Flow::Stop;
+            }
+        }
+        if let Some(&slot) = slots.get(&packet.to) {
+            let mut shared = switch.shared.lock().unwrap();
+            shared.mailboxes[slot].extend(&[packet.x, packet.y]);
+            switch.machines.notify_all();
+        } else if let Some(node) = self.nodes.get_mut(&packet.to) {
+            if node.receive(packet) == Flow::Stop {
+                flow = Flow::Stop;
+            }
+        } else {
+            self.dropped.push(packet);
+        }
+        flow
+    }
+
+    /// Routes packets until the network shuts down, see the module docs.
+    pub fn run(&mut self) -> Result<Finish, VmError> {
+        let machines = std::mem::take(&mut self.machines);
+        let slots = machines
+            .keys()
+            .enumerate()
+            .map(|(slot, &address)| (address, slot))
+            .collect::<BTreeMap<_, _>>();
+        let switch = Switch::default();
+        {
+            let mut shared = switch.shared.lock().unwrap();
+            shared.mailboxes = machines.keys().map(|&a| vec![a].into()).collect();
+            shared.blocked = vec![false; machines.len()];
+            shared.finished = vec![false; machines.len()];
+            shared.outbox = self.outbox.drain(..).collect();
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        thread::scope(|scope| {
+            for (slot, (address, comp)) in machines.into_iter().enumerate() {
+                let switch = &switch;
+                let cancel = cancel.clone();
+                scope.spawn(move || {
+                    let mailbox = Mailbox {
+                        switch,
+                        slot,
+                        polled: false,
+                    };
+                    let outbox = Outbox {
+                        switch,
+                        address,
+                        pending: vec![],
+                    };
+                    let mut comp = comp.with_io(mailbox, outbox);
+                    comp.set_cancel_flag(cancel);
+                    let result = comp.run();
+
+                    let mut shared = switch.shared.lock().unwrap();
+                    match result {
+                        Ok(State::Halted) => shared.halted = true,
+                        Err(error) => {
+                            shared.error.get_or_insert(error);
+                        }
+                        _ => {}
+                    }
+                    shared.finished[slot] = true;
+                    switch.supervisor.notify_one();
+                });
+            }
+
+            let finish = self.supervise(&switch, &slots);
+            switch.shared.lock().unwrap().shutdown = true;
+            cancel.store(true, Ordering::Relaxed);
+            switch.machines.notify_all();
+            finish
+        })
+    }
+
+    fn supervise(
+        &mut self,
+        switch: &Switch,
+        slots: &BTreeMap<i64, usize>,
+    ) -> Result<Finish, VmError> {
+        let mut shared = switch.shared.lock().unwrap();
+        loop {
+            if let Some(error) = shared.error.take() {
+                return Err(error);
+            }
+            if shared.halted {
+                return Ok(Finish::Halted);
+            }
+
+            if !shared.outbox.is_empty() {
+                let packets = shared.outbox.drain(..).collect::<Vec<_>>();
+                drop(shared);
+                for packet in packets {
+                    if self.route(switch, slots, packet) == Flow::Stop {
+                        return Ok(Finish::Stopped);
+                    }
+                }
+            } else if shared.idle() {
+                drop(shared);
+                let mut woken = false;
+                let addresses = self.nodes.keys().copied().collect::<Vec<_>>();
+                for address in addresses {
+                    if let Some(packet) = self.nodes.get_mut(&address).unwrap().idle() {
+                        woken = true;
+                        let packet = Packet {
+                            from: address,
+                            ..packet
+                        };
+                        if self.route(switch, slots, packet) == Flow::Stop {
+                            return Ok(Finish::Stopped);
+                        }
+                    }
+                }
+                if !woken {
+                    return Ok(Finish::Idle);
+                }
+            } else {
+                shared = switch.supervisor.wait(shared).unwrap();
+                continue;
+            }
+            shared = switch.shared.lock().unwrap();
+        }
+    }
+}
+
+#[test]
+fn it_routes_packets_between_threads() {
+    use crate::network::Nat;
+
+    // reads its address then forwards every packet it gets to address + 1
+    let relay = vec![
+        3, 26, 1001, 26, 1, 27, 3, 28, 1008, 28, -1, 30, 1005, 30, 6, 3, 29, 4, 27, 4, 28, 4, 29,
+        1105, 1, 6, 0, 0, 0, 0, 0,
+    ];
+    let packet = Packet {
+        from: -1,
+        to: 0,
+        x: 7,
+        y: 8,
+    };
+    let mut network = ThreadedNetwork::new();
+    for address in 0..4 {
+        network.add_machine(address, Computer::new(relay.clone()));
+    }
+    network.send(packet);
+    assert_eq!(network.run(), Ok(Finish::Idle));
+    assert_eq!(network.dropped().len(), 1);
+    assert_eq!((network.dropped()[0].from, network.dropped()[0].to), (3, 4));
+
+    let mut wakeups = vec![];
+    let mut network = ThreadedNetwork::new();
+    for address in 0..4 {
+        network.add_machine(address, Computer::new(relay.clone()));
+    }
+    network.add_node(4, Nat::new(0));
+    network.on_packet(|packet| {
+        if packet.from == 4 {
+            wakeups.push((packet.x, packet.y));
+        }
+        if wakeups.len() == 3 {
+            Flow::Stop
+        } else {
+            Flow::Continue
+        }
+    });
+    network.send(packet);
+    assert_eq!(network.run(), Ok(Finish::Stopped));
+    drop(network);
+    assert_eq!(wakeups, vec![(7, 8); 3]);
+}
+
+#[test]
+fn it_shuts_down_when_a_machine_halts() {
+    // machine 1 halts as soon as it gets a packet, machine 0 spins forever
+    let mut network = ThreadedNetwork::new();
+    network.add_machine(0, Computer::new(vec![1105, 1, 0]));
+    network.add_machine(
+        1,
+        Computer::new(vec![3, 20, 3, 20, 1008, 20, -1, 21, 1005, 21, 2, 99]),
+    );
+    network.send(Packet {
+        from: -1,
+        to: 1,
+        x: 1,
+        y: 2,
+    });
+    assert_eq!(network.run(), Ok(Finish::Halted));
+}
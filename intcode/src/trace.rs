@@ -0,0 +1,162 @@
+use crate::instruction::Opcode;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Everything one instruction did, as seen by a `Tracer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Instructions executed before this one, counted across filters so
+    /// traces of the same run line up.
+    pub step: u64,
+    pub ip: usize,
+    pub opcode: Opcode,
+    /// Parameter values after resolving their modes, or the target address
+    /// for the parameter written through.
+    pub operands: Vec<i64>,
+    pub write: Option<(usize, i64)>,
+    pub relative_base: Option<i64>,
+    pub input: Option<i64>,
+    pub output: Option<i64>,
+}
+
+impl Record {
+    /// Renders the record as a single JSON object, leaving out what the
+    /// instruction didn't do.
+    pub fn to_json(&self) -> String {
+        let operands = self
+            .operands
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        let mut json = format!(
+            "{{\"step\":{},\"ip\":{},\"opcode\":\"{}\",\"operands\":[{}]",
+            self.step,
+            self.ip,
+            self.opcode.mnemonic(),
+            operands.join(",")
+        );
+        if let Some((address, value)) = self.write {
+            json += &format!(",\"write\":{{\"address\":{},\"value\":{}}}", address, value);
+        }
+        if let Some(base) = self.relative_base {
+            json += &format!(",\"relative_base\":{}", base);
+        }
+        if let Some(input) = self.input {
+            json += &format!(",\"input\":{}", input);
+        }
+        if let Some(output) = self.output {
+            json += &format!(",\"output\":{}", output);
+        }
+        json + "}"
+    }
+}
+
+/// Writes a JSON line per executed instruction, see `Computer::set_tracer`.
+pub struct Tracer {
+    out: Box<dyn Write + Send>,
+    addresses: Option<Range<usize>>,
+    opcodes: Option<Vec<Opcode>>,
+    step: u64,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new<W: Write + Send + 'static>(out: W) -> Self {
+        Self {
+            out: Box::new(out),
+            addresses: None,
+            opcodes: None,
+            step: 0,
+            error: None,
+        }
+    }
+
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// A tracer writing to `<dir>/<name>.jsonl` when `INTCODE_TRACE` names a
+    /// directory, so drivers can be traced without changing them.
+    pub fn from_env(name: &str) -> Option<Self> {
+        let dir = env::var_os("INTCODE_TRACE")?;
+        let path = Path::new(&dir).join(format!("{}.jsonl", name));
+        match Self::to_file(&path) {
+            Ok(tracer) => Some(tracer),
+            Err(error) => panic!("could not create {}: {}", path.display(), error),
+        }
+    }
+
+    /// Only records instructions starting inside `addresses`.
+    pub fn addresses(mut self, addresses: Range<usize>) -> Self {
+        self.addresses = Some(addresses);
+        self
+    }
+
+    /// Only records the given kinds of instruction.
+    pub fn opcodes(mut self, opcodes: &[Opcode]) -> Self {
+        self.opcodes = Some(opcodes.to_vec());
+        self
+    }
+
+    /// Whether the instruction at `ip` should be recorded.
+    pub(crate) fn wants(&self, ip: usize, opcode: Opcode) -> bool {
+        let address_ok = self.addresses.as_ref().is_none_or(|r| r.contains(&ip));
+        let opcode_ok = self.opcodes.as_ref().is_none_or(|o| o.contains(&opcode));
+        address_ok && opcode_ok && self.error.is_none()
+    }
+
+    /// Write failures are kept until `flush` rather than faulting the
+    /// machine.
+    pub(crate) fn record(&mut self, mut record: Record) {
+        record.step = self.step;
+        self.step += 1;
+        if let Err(error) = writeln!(self.out, "{}", record.to_json()) {
+            self.error = Some(error);
+        }
+    }
+
+    /// Counts an instruction that was filtered out.
+    pub(crate) fn skip(&mut self) {
+        self.step += 1;
+    }
+
+    /// Flushes the trace, reporting the first write that failed.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.out.flush()
+    }
+}
+
+#[test]
+fn it_renders_only_what_happened() {
+    let record = Record {
+        step: 3,
+        ip: 4,
+        opcode: Opcode::Add,
+        operands: vec![1, -2, 7],
+        write: Some((7, -1)),
+        relative_base: None,
+        input: None,
+        output: None,
+    };
+    assert_eq!(
+        record.to_json(),
+        r#"{"step":3,"ip":4,"opcode":"ADD","operands":[1,-2,7],"write":{"address":7,"value":-1}}"#
+    );
+}
+
+#[test]
+fn it_filters_by_address_and_opcode() {
+    let tracer = Tracer::new(io::sink())
+        .addresses(2..10)
+        .opcodes(&[Opcode::Output]);
+    assert!(!tracer.wants(0, Opcode::Output));
+    assert!(!tracer.wants(2, Opcode::Add));
+    assert!(tracer.wants(2, Opcode::Output));
+    assert!(!tracer.wants(10, Opcode::Output));
+}
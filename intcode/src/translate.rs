@@ -0,0 +1,333 @@
+//! Ahead-of-time translation of a program into Rust source.
+//!
+//! Every basic block `basic_blocks` finds becomes a function, split again
+//! before each input so a machine waiting for input resumes in translated
+//! code. The generated `run` dispatches between them on the instruction
+//! pointer and hands the machine back to `Computer::run` whenever it can't
+//! go on by itself: a jump to code it doesn't know, a write to a code cell
+//! it was translated from, or anything that would fault. The interpreter
+//! redoes that instruction, so faults and self-modifying code behave
+//! exactly as they would interpreted.
+//!
+//! Parameters that the program patches through a fixed address, a common
+//! way to index arrays, are read from memory rather than baked in.
+//!
+//! Build scripts write the module out for `include!`:
+//!
+//! ```text
+//! let program = intcode::load_registers("input.txt");
+//! fs::write(out_dir.join("program.rs"), intcode::translate(&program))?;
+//! ```
+
+use crate::cfg::basic_blocks;
+use crate::disasm::Line;
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::collections::BTreeSet;
+
+const RUNTIME: &str = r#"
+enum Exit {
+    Jump(usize),
+    Halt(usize),
+    Wait(usize),
+    Interpret(usize),
+}
+
+macro_rules! or_interpret {
+    ($at:expr, $value:expr) => {
+        match $value {
+            Some(value) => value,
+            None => return Exit::Interpret($at),
+        }
+    };
+}
+
+fn load<I, O>(comp: &Computer<I, O>, address: i64) -> Option<i64> {
+    if address < 0 {
+        return None;
+    }
+    comp.registers.get(address as usize).ok()
+}
+
+fn writable<I, O>(comp: &mut Computer<I, O>, address: i64) -> Option<&mut i64> {
+    if address < 0 || FIXED.get(address as usize) == Some(&true) {
+        return None;
+    }
+    comp.registers.get_mut(address as usize).ok()
+}
+
+fn jump(at: usize, target: i64) -> Exit {
+    if target < 0 {
+        Exit::Interpret(at)
+    } else {
+        Exit::Jump(target as usize)
+    }
+}
+
+/// Whether a code cell the translation depends on has been changed.
+fn patched<I, O>(comp: &Computer<I, O>) -> bool {
+    comp.registers.len() < PROGRAM.len()
+        || PROGRAM
+            .iter()
+            .zip(FIXED.iter())
+            .enumerate()
+            .any(|(address, (&original, &fixed))| fixed && comp.registers.get(address) != Ok(original))
+}
+
+/// Runs like `Computer::run`, in translated code where it can.
+pub fn run<I: InputSource, O: OutputSink>(comp: &mut Computer<I, O>) -> Result<State, VmError> {
+    if comp.needs_interpreter() || patched(comp) {
+        return comp.run();
+    }
+    let mut ip = comp.position();
+    let mut rb = comp.relative_base();
+    loop {
+        let exit = dispatch(comp, ip, &mut rb);
+        let (at, state) = match exit {
+            Exit::Jump(target) => {
+                ip = target;
+                continue;
+            }
+            Exit::Halt(at) => (at, State::Halted),
+            Exit::Wait(at) => (at, State::Waiting),
+            Exit::Interpret(at) => {
+                comp.set_position(at);
+                comp.set_relative_base(rb);
+                return comp.run();
+            }
+        };
+        comp.set_position(at);
+        comp.set_relative_base(rb);
+        comp.state = state.clone();
+        return Ok(state);
+    }
+}
+"#;
+
+/// Where instructions start and which parameters get patched at run time.
+struct Code {
+    fixed: Vec<bool>,
+    dynamic: BTreeSet<usize>,
+}
+
+impl Code {
+    fn new(program: &[i64], instructions: &[(usize, Instruction, Vec<i64>)]) -> Self {
+        let mut fixed = vec![false; program.len()];
+        let mut params = BTreeSet::new();
+        for (address, _, operands) in instructions {
+            fixed[*address] = true;
+            params.extend(address + 1..=address + operands.len());
+        }
+        let mut dynamic = BTreeSet::new();
+        for (_, instruction, operands) in instructions {
+            if let Some(i) = instruction.opcode.writes() {
+                let target = operands[i];
+                if instruction.modes[i] == Mode::Position
+                    && target >= 0
+                    && params.contains(&(target as usize))
+                {
+                    dynamic.insert(target as usize);
+                }
+            }
+        }
+        for &param in &params {
+            fixed[param] = !dynamic.contains(&param);
+        }
+        Self { fixed, dynamic }
+    }
+
+    /// The raw parameter in `cell`.
+    fn param(&self, at: usize, cell: usize, value: i64) -> String {
+        if self.dynamic.contains(&cell) {
+            format!("or_interpret!({}, load(comp, {}))", at, cell)
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn address(&self, at: usize, cell: usize, mode: Mode, value: i64) -> String {
+        let param = self.param(at, cell, value);
+        match mode {
+            Mode::Relative if param == "0" => "*rb".to_string(),
+            Mode::Relative => format!("or_interpret!({}, rb.checked_add({}))", at, param),
+            _ => param,
+        }
+    }
+
+    fn read(&self, at: usize, cell: usize, mode: Mode, value: i64) -> String {
+        match mode {
+            Mode::Immediate => self.param(at, cell, value),
+            _ => format!(
+                "or_interpret!({}, load(comp, {}))",
+                at,
+                self.address(at, cell, mode, value)
+            ),
+        }
+    }
+}
+
+/// The statements for one instruction, or the block's final expression
+/// for a halt.
+fn statements(code: &Code, at: usize, instruction: &Instruction, params: &[i64]) -> String {
+    let opcode = instruction.opcode;
+    let modes = instruction.modes;
+    let cell = |i: usize| at + 1 + i;
+    let read = |i: usize| code.read(at, cell(i), modes[i], params[i]);
+    let address = |i: usize| code.address(at, cell(i), modes[i], params[i]);
+    let store = |value: &str| {
+        format!(
+            "    *or_interpret!({}, writable(comp, {})) = {};\n",
+            at,
+            address(2),
+            value
+        )
+    };
+    let operands = || format!("    let a = {};\n    let b = {};\n", read(0), read(1));
+    let branch = |test: &str| {
+        format!(
+            "    if a {} 0 {{\n        return jump({}, b);\n    }}\n",
+            test, at
+        )
+    };
+    match opcode {
+        Opcode::Add => operands() + &store("i64::wrapping_add(a, b)"),
+        Opcode::Mult => operands() + &store("i64::wrapping_mul(a, b)"),
+        Opcode::LessThan => operands() + &store("i64::from(a < b)"),
+        Opcode::EqualTo => operands() + &store("i64::from(a == b)"),
+        Opcode::JumpIfTrue => operands() + &branch("!="),
+        Opcode::JumpIfFalse => operands() + &branch("=="),
+        Opcode::Input => {
+            let mut input = format!("    let address = {};\n", address(0));
+            input += &format!("    or_interpret!({}, writable(comp, address));\n", at);
+            input += "    match comp.inputs.next_input() {\n";
+            input += &format!(
+                "        Some(input) => *or_interpret!({}, writable(comp, address)) = input,\n",
+                at
+            );
+            input += &format!("        None => return Exit::Wait({}),\n    }}\n", at);
+            input
+        }
+        Opcode::Output => format!(
+            "    let a = {};\n    comp.outputs.send_output(a);\n",
+            read(0)
+        ),
+        Opcode::UpdateBase => format!(
+            "    *rb = or_interpret!({}, rb.checked_add({}));\n",
+            at,
+            read(0)
+        ),
+        Opcode::Halt => format!("    Exit::Halt({})\n", at),
+    }
+}
+
+/// Translates `program` into the source of a Rust module exposing
+/// `run(&mut Computer) -> Result<State, VmError>`, a drop-in replacement for
+/// `Computer::run` on machines loaded with `program`.
+///
+/// Fuel, cancel flags, tracers, profilers, journals and arithmetic other
+/// than wrapping see every instruction, so machines using them always run
+/// interpreted.
+pub fn translate(program: &[i64]) -> String {
+    let mut blocks = vec![];
+    for block in basic_blocks(program) {
+        let mut lines = vec![];
+        for line in block.lines {
+            if let Line::Code {
+                address,
+                instruction,
+                params,
+            } = line
+            {
+                // split before inputs so waiting machines resume here
+                if instruction.opcode == Opcode::Input && !lines.is_empty() {
+                    blocks.push(std::mem::take(&mut lines));
+                }
+                lines.push((address, instruction, params));
+            }
+        }
+        blocks.push(lines);
+    }
+    let code = Code::new(
+        program,
+        &blocks.iter().flatten().cloned().collect::<Vec<_>>(),
+    );
+
+    let join = |values: Vec<String>| values.join(", ");
+    let mut source =
+        "// Translated from an Intcode program by `intcode::translate`.\n\n".to_string();
+    source += "use intcode::{Computer, InputSource, OutputSink, State, VmError};\n\n";
+    source += &format!(
+        "static PROGRAM: [i64; {}] = [{}];\n\n",
+        program.len(),
+        join(program.iter().map(|v| v.to_string()).collect())
+    );
+    source += "/// Code cells the translation was made from, writing one hands the\n";
+    source += "/// machine to the interpreter.\n";
+    source += &format!(
+        "static FIXED: [bool; {}] = [{}];\n",
+        program.len(),
+        join(code.fixed.iter().map(|f| f.to_string()).collect())
+    );
+    source += RUNTIME;
+
+    source += "\nfn dispatch<I: InputSource, O: OutputSink>(\n";
+    source += "    comp: &mut Computer<I, O>,\n    ip: usize,\n    rb: &mut i64,\n) -> Exit {\n";
+    source += "    match ip {\n";
+    for lines in &blocks {
+        let start = lines[0].0;
+        source += &format!("        {} => block_{}(comp, rb),\n", start, start);
+    }
+    source += "        _ => Exit::Interpret(ip),\n    }\n}\n";
+
+    for lines in &blocks {
+        let start = lines[0].0;
+        let mut body = String::new();
+        let mut uses = String::new();
+        for (address, instruction, params) in lines {
+            let text = Line::Code {
+                address: *address,
+                instruction: *instruction,
+                params: params.clone(),
+            };
+            let statements = statements(&code, *address, instruction, params);
+            body += &format!("    // {}\n{}", text, statements);
+            uses += &statements;
+        }
+        let (last, last_instruction, last_params) = lines.last().unwrap();
+        if last_instruction.opcode != Opcode::Halt {
+            body += &format!("    Exit::Jump({})\n", last + 1 + last_params.len());
+        }
+        source += &format!(
+            "\nfn block_{}<I: InputSource, O: OutputSink>({}: &mut Computer<I, O>, {}: &mut i64) -> Exit {{\n{}}}\n",
+            start,
+            if uses.contains("comp") { "comp" } else { "_comp" },
+            if uses.contains("rb") { "rb" } else { "_rb" },
+            body
+        );
+    }
+    source
+}
+
+#[test]
+fn it_translates_a_block_per_entry_point() {
+    // reads a number and echoes it back until it reads 0
+    let program = [3, 9, 4, 9, 1005, 9, 0, 99, 0, 0];
+    let source = translate(&program);
+    assert!(source.contains("        0 => block_0(comp, rb),\n"));
+    assert!(source.contains("        7 => block_7(comp, rb),\n"));
+    assert!(source.contains("fn block_7<I: InputSource, O: OutputSink>(_comp"));
+    assert!(source.contains("None => return Exit::Wait(0),"));
+    assert!(source.contains("static FIXED: [bool; 10] = [true, true, true, true, true, true, true, true, false, false];"));
+}
+
+#[test]
+fn it_reads_patched_parameters_from_memory() {
+    // copies [10] into the parameter of the output, then outputs through it
+    let program = [1001, 10, 0, 5, 4, 0, 99, 0, 0, 0, 9];
+    let source = translate(&program);
+    assert!(
+        source.contains("let a = or_interpret!(4, load(comp, or_interpret!(4, load(comp, 5))));")
+    );
+    assert!(
+        source.contains("[true, true, true, true, true, false, true, false, false, false, false]")
+    );
+}